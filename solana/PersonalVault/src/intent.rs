@@ -0,0 +1,151 @@
+// 投资者签名的交易意图，通过指令 sysvar 内省同一交易中的 Ed25519 程序指令验证
+// Ed25519 指令数据布局参考 solana-sdk 的 ed25519_instruction:
+// num_signatures(1) + padding(1) + Ed25519SignatureOffsets(7 * u16) * num_signatures + 公钥/签名/消息数据
+
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+/// Ed25519 签名偏移结构的起始位置
+const SIGNATURE_OFFSETS_START: usize = 2;
+/// Ed25519SignatureOffsets 的大小
+const SIGNATURE_OFFSETS_SIZE: usize = 14;
+/// 偏移指向 Ed25519 指令自身数据时的指令索引
+const CURRENT_INSTRUCTION_INDEX: u16 = u16::MAX;
+
+/// 交易意图，签名消息为其 borsh 序列化结果
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct TradeIntent {
+    /// 金库地址 (防止跨金库重放)
+    pub vault: Pubkey,
+    /// 输入代币
+    pub token_in: Pubkey,
+    /// 输出代币
+    pub token_out: Pubkey,
+    /// 输入金额
+    pub amount_in: u64,
+    /// 最小输出金额
+    pub min_out: u64,
+    /// 过期时间戳 (秒)
+    pub expiry: i64,
+    /// 随机数，必须大于金库记录的上一个随机数
+    pub nonce: u64,
+}
+
+/// 从指令数据中按偏移读取 u16
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes: [u8; 2] = data
+        .get(offset..offset + 2)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(ErrorCode::InvalidIntentSignature)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+/// 从指令数据中按偏移和长度截取
+fn read_slice(data: &[u8], offset: u16, len: usize) -> Result<&[u8]> {
+    let start = offset as usize;
+    data.get(start..start + len)
+        .ok_or_else(|| error!(ErrorCode::InvalidIntentSignature))
+}
+
+/// 解析只包含一个签名的 Ed25519 指令，返回签名公钥与被签名的消息
+/// 所有偏移必须指向该指令自身的数据，避免引用其他指令中的内容
+pub fn parse_ed25519_instruction(data: &[u8]) -> Result<(Pubkey, &[u8])> {
+    require!(
+        data.len() >= SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SIZE,
+        ErrorCode::InvalidIntentSignature
+    );
+    require!(data[0] == 1, ErrorCode::InvalidIntentSignature);
+
+    let offsets = SIGNATURE_OFFSETS_START;
+    let signature_instruction_index = read_u16(data, offsets + 2)?;
+    let public_key_offset = read_u16(data, offsets + 4)?;
+    let public_key_instruction_index = read_u16(data, offsets + 6)?;
+    let message_data_offset = read_u16(data, offsets + 8)?;
+    let message_data_size = read_u16(data, offsets + 10)?;
+    let message_instruction_index = read_u16(data, offsets + 12)?;
+
+    require!(
+        signature_instruction_index == CURRENT_INSTRUCTION_INDEX
+            && public_key_instruction_index == CURRENT_INSTRUCTION_INDEX
+            && message_instruction_index == CURRENT_INSTRUCTION_INDEX,
+        ErrorCode::InvalidIntentSignature
+    );
+
+    let public_key = read_slice(data, public_key_offset, 32)?;
+    let message = read_slice(data, message_data_offset, message_data_size as usize)?;
+
+    let public_key =
+        Pubkey::try_from(public_key).map_err(|_| error!(ErrorCode::InvalidIntentSignature))?;
+
+    Ok((public_key, message))
+}
+
+/// 解析签名消息中的交易意图
+pub fn decode_trade_intent(message: &[u8]) -> Result<TradeIntent> {
+    TradeIntent::try_from_slice(message).map_err(|_| error!(ErrorCode::InvalidIntentSignature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按 solana-sdk 的布局构造单签名 Ed25519 指令数据 (签名内容不参与解析)
+    fn ed25519_data(public_key: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+        let public_key_offset = (SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SIZE) as u16;
+        let signature_offset = public_key_offset + 32;
+        let message_data_offset = signature_offset + 64;
+
+        let mut data = vec![1u8, 0u8];
+        data.extend_from_slice(&signature_offset.to_le_bytes());
+        data.extend_from_slice(&instruction_index.to_le_bytes());
+        data.extend_from_slice(&public_key_offset.to_le_bytes());
+        data.extend_from_slice(&instruction_index.to_le_bytes());
+        data.extend_from_slice(&message_data_offset.to_le_bytes());
+        data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+        data.extend_from_slice(&instruction_index.to_le_bytes());
+        data.extend_from_slice(public_key.as_ref());
+        data.extend_from_slice(&[9u8; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    fn intent() -> TradeIntent {
+        TradeIntent {
+            vault: Pubkey::new_unique(),
+            token_in: Pubkey::new_unique(),
+            token_out: Pubkey::new_unique(),
+            amount_in: 1_000_000,
+            min_out: 990_000,
+            expiry: 1_700_000_000,
+            nonce: 7,
+        }
+    }
+
+    #[test]
+    fn parses_signed_intent() {
+        let signer = Pubkey::new_unique();
+        let intent = intent();
+        let message = intent.try_to_vec().unwrap();
+        let data = ed25519_data(&signer, &message, CURRENT_INSTRUCTION_INDEX);
+
+        let (public_key, parsed_message) = parse_ed25519_instruction(&data).unwrap();
+        assert_eq!(public_key, signer);
+        assert_eq!(decode_trade_intent(parsed_message).unwrap(), intent);
+    }
+
+    #[test]
+    fn rejects_offsets_into_other_instructions() {
+        let message = intent().try_to_vec().unwrap();
+        let data = ed25519_data(&Pubkey::new_unique(), &message, 0);
+        assert!(parse_ed25519_instruction(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let message = intent().try_to_vec().unwrap();
+        let data = ed25519_data(&Pubkey::new_unique(), &message, CURRENT_INSTRUCTION_INDEX);
+        assert!(parse_ed25519_instruction(&data[..data.len() - 1]).is_err());
+        assert!(decode_trade_intent(&message[..message.len() - 1]).is_err());
+    }
+}
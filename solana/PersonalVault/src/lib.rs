@@ -1,7 +1,13 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 use std::str::FromStr;
 use solana_program::program::invoke;
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+pub mod intent;
+pub mod oracle;
+use oracle::{clmm_twap, pyth, switchboard, OraclePrice};
 
 // Byreal CLMM 相关结构体定义
 // ⚠️ 重要警告：这些结构体应该与 Byreal CLMM 的实际定义保持一致
@@ -92,6 +98,25 @@ pub const BYREAL_SWAP_INSTRUCTION_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145,
 // swap_v2 指令的 discriminator: [43, 4, 237, 11, 26, 201, 30, 98]
 pub const BYREAL_SWAP_V2_INSTRUCTION_DISCRIMINATOR: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
 
+// 一笔 Byreal 交换在 remaining_accounts 中占用的账户数量:
+// amm_config, pool_state, input_token_account, output_token_account,
+// input_vault, output_vault, observation_state, tick_array
+pub const BYREAL_SWAP_LEG_ACCOUNTS: usize = 8;
+
+// 紧急退出时每个卖出仓位在 remaining_accounts 中占用的账户数量
+pub const EMERGENCY_EXIT_ACCOUNTS_PER_LEG: usize = BYREAL_SWAP_LEG_ACCOUNTS;
+
+// 每个金库最多登记的附加机器人数量
+pub const MAX_VAULT_BOTS: usize = 4;
+
+// 机器人权限位
+pub const BOT_PERMISSION_SWAP: u8 = 1 << 0;
+pub const BOT_PERMISSION_LP: u8 = 1 << 1;
+pub const BOT_PERMISSION_LEND: u8 = 1 << 2;
+pub const BOT_PERMISSION_REBALANCE: u8 = 1 << 3;
+pub const BOT_PERMISSION_ALL: u8 =
+    BOT_PERMISSION_SWAP | BOT_PERMISSION_LP | BOT_PERMISSION_LEND | BOT_PERMISSION_REBALANCE;
+
 declare_id!("5DSNTh2tDqJdH2MrvFAHMQxBMRmsbFVgE56JQ6fPqkaY");
 
 /// 代币余额结构
@@ -101,8 +126,42 @@ pub struct TokenBalance {
     pub amount: u64,
 }
 
+/// 交易频率与名义金额限制 (0 表示不限制)
+/// 名义金额以输入代币的最小单位计
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct TradeLimits {
+    /// 每个时间窗口内允许的最大交易次数
+    pub max_trades_per_window: u32,
+    /// 交易次数时间窗口长度 (slot)
+    pub trade_window_slots: u64,
+    /// 单笔交易最大名义金额
+    pub max_notional_per_trade: u64,
+    /// 每日最大名义金额
+    pub max_daily_notional: u64,
+    /// 当前交易次数窗口起始 slot
+    pub window_start_slot: u64,
+    /// 当前窗口内已执行交易次数
+    pub trades_in_window: u32,
+    /// 当前日窗口起始时间戳 (秒)
+    pub day_start_timestamp: i64,
+    /// 当前日窗口内已累计名义金额
+    pub daily_notional: u64,
+}
+
+/// 附加机器人条目：权限位掩码 + 该机器人单独的交易限制
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BotEntry {
+    /// 机器人地址
+    pub key: Pubkey,
+    /// 权限位掩码 (BOT_PERMISSION_*)
+    pub permissions: u8,
+    /// 该机器人单独的交易限制 (0 表示不限制)
+    pub limits: TradeLimits,
+}
+
 /// 个人金库账户结构
 #[account]
+#[derive(Default)]
 pub struct PersonalVault {
     /// 投资者地址
     pub investor: Pubkey,
@@ -118,674 +177,6456 @@ pub struct PersonalVault {
     pub is_initialized: bool,
     /// 代币余额列表
     pub balances: Vec<TokenBalance>,
+    /// 策略是否启用 (对应 UniswapVault 的 strategyEnabled)
+    pub strategy_enabled: bool,
+    /// 机器人交易限制
+    pub trade_limits: TradeLimits,
+    /// 是否启用预言机最小输出检查
+    pub oracle_check_enabled: bool,
+    /// 相对预言机价格允许的最大滑点 (基点)
+    pub max_oracle_slippage_bps: u16,
+    /// 待接受的新管理员地址 (Pubkey::default() 表示无)
+    pub pending_admin: Pubkey,
+    /// 平台运营方地址 (Pubkey::default() 表示无)
+    pub operator: Pubkey,
+    /// 附加机器人列表，每个机器人按权限位掩码授权
+    pub bots: Vec<BotEntry>,
+    /// 敏感配置变更的延迟时间 (秒，0 表示不启用时间锁)
+    pub timelock_seconds: i64,
+    /// 本金库的交易费率 (百万分之一，None 表示使用全局默认费率)
+    pub fee_rate_override: Option<u64>,
+    /// 是否要求交易信号附带投资者签名的交易意图
+    pub require_signed_intents: bool,
+    /// 策略签名者地址，可代替投资者签名交易意图 (Pubkey::default() 表示无)
+    pub intent_signer: Pubkey,
+    /// 已使用的最大交易意图随机数
+    pub last_intent_nonce: u64,
+    /// 最近执行的交易信号 ID 环形缓冲区 (0 表示空位)
+    pub recent_signal_ids: [u64; RECENT_SIGNAL_IDS],
+    /// 环形缓冲区下一个写入位置
+    pub signal_id_cursor: u8,
 }
 
-/// 内部函数：获取代币余额
-fn get_token_balance(vault: &PersonalVault, token: Pubkey) -> u64 {
-    msg!("查找代币余额，代币地址: {}", token);
-    msg!("当前金库中代币数量: {}", vault.balances.len());
-    
-    for (index, balance) in vault.balances.iter().enumerate() {
-        msg!("检查第{}个代币: {}", index + 1, balance.token);
-        if balance.token == token {
-            msg!("找到代币，余额: {}", balance.amount);
-            return balance.amount;
-        }
-    }
-    
-    msg!("未找到代币，返回0");
-    0
+// 每个金库记录的最近交易信号 ID 数量
+pub const RECENT_SIGNAL_IDS: usize = 32;
+
+/// 内部函数：拒绝重复的交易信号 ID，并写入最近执行的 ID 环形缓冲区
+fn record_signal_id(vault: &mut PersonalVault, signal_id: u64) -> Result<()> {
+    require!(signal_id != 0, ErrorCode::InvalidSignalId);
+    require!(
+        !vault.recent_signal_ids.contains(&signal_id),
+        ErrorCode::DuplicateSignalId
+    );
+
+    let cursor = vault.signal_id_cursor as usize % RECENT_SIGNAL_IDS;
+    vault.recent_signal_ids[cursor] = signal_id;
+    vault.signal_id_cursor = ((cursor + 1) % RECENT_SIGNAL_IDS) as u8;
+    msg!("记录交易信号 ID: {}，缓冲区位置: {}", signal_id, cursor);
+    Ok(())
 }
 
-/// 内部函数：设置代币余额
-fn set_token_balance(vault: &mut PersonalVault, token: Pubkey, amount: u64) {
-    msg!("设置代币余额，代币地址: {}, 新余额: {}", token, amount);
-    
-    for (index, balance) in vault.balances.iter_mut().enumerate() {
-        if balance.token == token {
-            msg!("更新现有代币余额，索引: {}", index);
-            let old_amount = balance.amount;
-            balance.amount = amount;
-            msg!("余额从 {} 更新为 {}", old_amount, amount);
-            return;
+/// 金库角色 (对应 MultiVault 中 ORACLE_ROLE / STRATEGY_MANAGER_ROLE 的角色划分)
+/// - Investor: 存款、取款、策略开关、交易限制与预言机检查、紧急退出
+/// - Admin: 管理员转移、任命运营方、设置机器人、交易限制与预言机检查
+/// - Operator: 设置机器人、暂停策略，没有存取款权限
+/// - Bot: 执行交易信号 (主机器人拥有全部权限，附加机器人按权限位掩码授权)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VaultRole {
+    Investor,
+    Admin,
+    Operator,
+    Bot,
+}
+
+impl PersonalVault {
+    /// 检查地址是否持有指定角色
+    pub fn has_role(&self, role: VaultRole, key: &Pubkey) -> bool {
+        let holder = match role {
+            VaultRole::Investor => self.investor,
+            VaultRole::Admin => self.admin,
+            VaultRole::Operator => self.operator,
+            VaultRole::Bot => return self.bot_permissions(key).is_some(),
+        };
+        holder != Pubkey::default() && holder == *key
+    }
+
+    /// 查询机器人的权限位掩码，非机器人返回 None
+    pub fn bot_permissions(&self, key: &Pubkey) -> Option<u8> {
+        if self.bot != Pubkey::default() && self.bot == *key {
+            return Some(BOT_PERMISSION_ALL);
         }
+        self.bots
+            .iter()
+            .find(|entry| entry.key == *key)
+            .map(|entry| entry.permissions)
     }
-    
-    // 如果代币不存在，添加新条目
-    msg!("代币不存在，添加新代币到余额列表");
-    vault.balances.push(TokenBalance {
-        token,
-        amount,
-    });
-    msg!("新代币已添加，当前代币总数: {}", vault.balances.len());
 }
 
-/// 个人金库程序
-/// 直接管理用户的个人金库，包含存款、取款、交换等功能
-#[program]
-pub mod personal_vault {
-    use super::*;
+/// 内部函数：要求调用者是持有指定权限的机器人
+fn require_bot_permission(vault: &PersonalVault, key: Pubkey, permission: u8) -> Result<()> {
+    let permissions = vault.bot_permissions(&key).ok_or(ErrorCode::OnlyBot)?;
+    require!(permissions & permission == permission, ErrorCode::BotPermissionDenied);
+    Ok(())
+}
 
-    /// 创建余额管理器 (对应 Aptos 的 create_balance_manager)
-    pub fn create_balance_manager(
-        ctx: Context<CreateBalanceManager>,
-        bot_address: Pubkey,      // 机器人合约地址，用于执行自动交易
-        swap_router: Pubkey,      // DEX 路由器地址（如 Raydium、Orca）
-        wrapped_native: Pubkey,   // 包装 SOL 代币地址
-    ) -> Result<()> {
-        msg!("开始创建余额管理器...");
-        msg!("用户地址: {}", ctx.accounts.user.key());
-        msg!("机器人地址: {}", bot_address);
-        msg!("交换路由器: {}", swap_router);
-        msg!("包装原生代币: {}", wrapped_native);
-        
-        // 验证参数
-        require!(bot_address != Pubkey::default(), ErrorCode::InvalidBotAddress);
-        require!(swap_router != Pubkey::default(), ErrorCode::InvalidSwapRouter);
-        require!(wrapped_native != Pubkey::default(), ErrorCode::InvalidWrappedNative);
+/// 临时机器人会话账户 (每个会话一个 PDA)，会话密钥在有效期内可以调用 send_trade_signal
+#[account]
+pub struct BotSession {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 会话密钥地址
+    pub session_key: Pubkey,
+    /// 授权者地址 (投资者或管理员)
+    pub granted_by: Pubkey,
+    /// 过期 slot (0 表示不按 slot 过期)
+    pub expires_slot: u64,
+    /// 过期时间戳 (秒，0 表示不按时间过期)
+    pub expires_at: i64,
+    /// 最大交易次数 (0 表示不限制)
+    pub max_trades: u32,
+    /// 已执行交易次数
+    pub trades_used: u32,
+    /// PDA bump
+    pub bump: u8,
+}
 
-        msg!("参数验证通过，设置金库数据...");
+/// 内部函数：检查会话是否有效并记录一次交易
+fn consume_bot_session(session: &mut BotSession, slot: u64, timestamp: i64) -> Result<()> {
+    msg!("检查会话密钥: {}", session.session_key);
 
-        let vault = &mut ctx.accounts.vault;
-        
-        // 设置金库数据
-        vault.investor = ctx.accounts.user.key();
-        vault.admin = ctx.accounts.user.key();
-        vault.bot = bot_address;
-        vault.swap_router = swap_router;
-        vault.wrapped_native = wrapped_native;
-        vault.is_initialized = true;
+    if session.expires_slot > 0 {
+        require!(slot < session.expires_slot, ErrorCode::SessionExpired);
+    }
+    if session.expires_at > 0 {
+        require!(timestamp < session.expires_at, ErrorCode::SessionExpired);
+    }
+    if session.max_trades > 0 {
+        require!(session.trades_used < session.max_trades, ErrorCode::SessionTradeLimitReached);
+    }
 
-        msg!("余额管理器创建完成!");
-        msg!("金库地址: {}", ctx.accounts.vault.key());
+    session.trades_used += 1;
+    msg!("会话已执行交易次数: {}", session.trades_used);
+    Ok(())
+}
 
-        emit!(BalanceManagerCreatedEvent {
-            user: ctx.accounts.user.key(),
-            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
-        });
+/// 内部函数：在同一交易中查找投资者或策略签名者签名的 Ed25519 交易意图，
+/// 校验其与交易信号参数一致、未过期且随机数未被使用，然后记录随机数
+#[allow(clippy::too_many_arguments)]
+fn verify_signed_intent(
+    vault: &mut PersonalVault,
+    vault_key: Pubkey,
+    instructions_sysvar: &AccountInfo,
+    token_in: Pubkey,
+    token_out: Pubkey,
+    amount_in: u64,
+    amount_out_minimum: u64,
+    timestamp: i64,
+) -> Result<()> {
+    msg!("检查签名交易意图...");
 
-        Ok(())
-    }
+    let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+    for index in 0..current_index {
+        let instruction = load_instruction_at_checked(index, instructions_sysvar)?;
+        if instruction.program_id != solana_program::ed25519_program::ID {
+            continue;
+        }
 
-    /// 设置机器人地址
-    pub fn set_bot(
-        ctx: Context<SetBot>,
-        new_bot_address: Pubkey,
-    ) -> Result<()> {
-        msg!("开始设置机器人地址...");
-        msg!("用户地址: {}", ctx.accounts.user.key());
-        msg!("新机器人地址: {}", new_bot_address);
-        
-        require!(new_bot_address != Pubkey::default(), ErrorCode::InvalidBotAddress);
-        
-        let vault = &mut ctx.accounts.vault;
-        let old_bot_address = vault.bot;
-        
-        msg!("当前机器人地址: {}", old_bot_address);
-        
-        require!(new_bot_address != old_bot_address, ErrorCode::SameBotAddress);
-        require!(ctx.accounts.user.key() == vault.admin, ErrorCode::Unauthorized);
+        let (signer, message) = intent::parse_ed25519_instruction(&instruction.data)?;
+        let authorized = signer == vault.investor
+            || (vault.intent_signer != Pubkey::default() && signer == vault.intent_signer);
+        if !authorized {
+            msg!("跳过未授权签名者的 Ed25519 指令: {}", signer);
+            continue;
+        }
 
-        msg!("验证通过，更新机器人地址...");
-        
-        vault.bot = new_bot_address;
-        
-        msg!("机器人地址更新完成!");
+        let trade_intent = intent::decode_trade_intent(message)?;
+        msg!("交易意图签名者: {}，随机数: {}", signer, trade_intent.nonce);
 
-        Ok(())
+        require!(
+            trade_intent.vault == vault_key
+                && trade_intent.token_in == token_in
+                && trade_intent.token_out == token_out
+                && trade_intent.amount_in == amount_in
+                && trade_intent.min_out == amount_out_minimum,
+            ErrorCode::IntentMismatch
+        );
+        require!(timestamp <= trade_intent.expiry, ErrorCode::IntentExpired);
+        require!(trade_intent.nonce > vault.last_intent_nonce, ErrorCode::IntentNonceUsed);
+
+        vault.last_intent_nonce = trade_intent.nonce;
+        return Ok(());
     }
 
-    /// 设置管理员
-    pub fn set_admin(
-        ctx: Context<SetAdmin>,
-        new_admin: Pubkey,
-    ) -> Result<()> {
-        msg!("开始设置管理员...");
-        msg!("用户地址: {}", ctx.accounts.user.key());
-        msg!("新管理员地址: {}", new_admin);
-        
-        require!(new_admin != Pubkey::default(), ErrorCode::InvalidAdminAddress);
-        
-        let vault = &mut ctx.accounts.vault;
-        let old_admin = vault.admin;
-        
-        msg!("当前管理员地址: {}", old_admin);
-        
-        require!(new_admin != old_admin, ErrorCode::SameAdminAddress);
-        require!(ctx.accounts.user.key() == vault.admin, ErrorCode::Unauthorized);
+    err!(ErrorCode::MissingSignedIntent)
+}
 
-        msg!("验证通过，更新管理员地址...");
-        
-        vault.admin = new_admin;
-        
-        msg!("管理员地址更新完成!");
-        
-        Ok(())
-    }
+// 时间锁延迟上限 (30 天)
+pub const MAX_TIMELOCK_SECONDS: i64 = 30 * SECONDS_PER_DAY;
 
-    /// 用户存款函数 (对应 Aptos 的 user_deposit)
-    pub fn user_deposit(
-        ctx: Context<UserDeposit>,
-        amount: u64,
-    ) -> Result<()> {
-        let vault = &mut ctx.accounts.vault;
-        
-        msg!("开始用户存款操作...");
-        msg!("用户地址: {}", ctx.accounts.user.key());
-        msg!("代币地址: {}", ctx.accounts.mint.key());
-        msg!("存款金额: {}", amount);
-        
-        // 验证调用者是投资者
-        require!(ctx.accounts.user.key() == vault.investor, ErrorCode::OnlyInvestor);
-        require!(amount > 0, ErrorCode::InvalidAmount);
-        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+/// 受时间锁保护的配置变更类型
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigChangeKind {
+    /// 更换主机器人 (new_address)
+    Bot,
+    /// 提议新管理员 (new_address)，执行后仍需新管理员 accept_admin
+    Admin,
+    /// 更换交换路由器 (new_address)
+    SwapRouter,
+    /// 管理员授予会话密钥 (new_address 为会话密钥，参数见 session_grant)
+    Session,
+    /// 设置本金库交易费率 (new_fee_rate，None 表示恢复全局默认费率)，仅平台管理员可以提议
+    FeeRate,
+}
 
-        msg!("验证通过，开始更新余额...");
-        
-        // 更新余额
-        let current_balance = get_token_balance(vault, ctx.accounts.mint.key());
-        msg!("当前余额: {}", current_balance);
-        
-        set_token_balance(vault, ctx.accounts.mint.key(), current_balance + amount);
-        let new_balance = get_token_balance(vault, ctx.accounts.mint.key());
-        msg!("更新后余额: {}", new_balance);
+/// 排队中的会话密钥授权参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct SessionGrant {
+    /// 过期 slot (0 表示不按 slot 过期)
+    pub expires_slot: u64,
+    /// 过期时间戳 (秒，0 表示不按时间过期)
+    pub expires_at: i64,
+    /// 最大交易次数 (0 表示不限制)
+    pub max_trades: u32,
+}
 
-        emit!(UserDepositEvent {
-            user: ctx.accounts.user.key(),
-            asset_metadata: ctx.accounts.mint.key(), // 对应 Aptos 的 Object<Metadata>
-            amount,
-            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
-        });
+/// 排队中的配置变更账户 (每个金库每种变更类型一个 PDA)
+#[account]
+pub struct QueuedConfigChange {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 变更类型
+    pub kind: ConfigChangeKind,
+    /// 新地址
+    pub new_address: Pubkey,
+    /// 会话密钥授权参数 (仅 Session)
+    pub session_grant: Option<SessionGrant>,
+    /// 新的金库交易费率 (仅 FeeRate，百万分之一)
+    pub new_fee_rate: Option<u64>,
+    /// 提议者地址
+    pub proposed_by: Pubkey,
+    /// 可执行时间戳 (秒)
+    pub eta: i64,
+    /// PDA bump
+    pub bump: u8,
+}
 
-        msg!("用户存款操作完成!");
-        Ok(())
+/// 内部函数：各类配置变更的提议者角色
+fn config_change_roles(kind: ConfigChangeKind) -> &'static [VaultRole] {
+    match kind {
+        ConfigChangeKind::Bot => &[VaultRole::Admin, VaultRole::Operator],
+        ConfigChangeKind::Admin | ConfigChangeKind::SwapRouter | ConfigChangeKind::Session => {
+            &[VaultRole::Admin]
+        }
+        // 费率由平台管理员提议，不对应金库内的角色
+        ConfigChangeKind::FeeRate => &[],
     }
+}
 
-    /// 用户取款函数 (对应 Aptos 的 user_withdraw)
-    pub fn user_withdraw(
-        ctx: Context<UserWithdraw>,
-        amount: u64,
-    ) -> Result<()> {
-        let vault = &mut ctx.accounts.vault;
-        
-        msg!("开始用户取款操作...");
-        msg!("用户地址: {}", ctx.accounts.user.key());
-        msg!("代币地址: {}", ctx.accounts.mint.key());
-        msg!("取款金额: {}", amount);
-        
-        // 验证调用者是投资者
-        require!(ctx.accounts.user.key() == vault.investor, ErrorCode::OnlyInvestor);
-        require!(amount > 0, ErrorCode::InvalidAmount);
-        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+/// 内部函数：校验配置变更参数，返回需要记录的会话授权参数 (仅 Session)
+fn validate_config_change(
+    vault: &PersonalVault,
+    kind: ConfigChangeKind,
+    new_address: Pubkey,
+    session_grant: Option<SessionGrant>,
+) -> Result<Option<SessionGrant>> {
+    match kind {
+        ConfigChangeKind::Bot => {
+            require!(new_address != Pubkey::default(), ErrorCode::InvalidBotAddress);
+            require!(new_address != vault.bot, ErrorCode::SameBotAddress);
+        }
+        ConfigChangeKind::Admin => {
+            require!(new_address != Pubkey::default(), ErrorCode::InvalidAdminAddress);
+            require!(new_address != vault.admin, ErrorCode::SameAdminAddress);
+        }
+        ConfigChangeKind::SwapRouter => {
+            require!(new_address != Pubkey::default(), ErrorCode::InvalidSwapRouter);
+        }
+        ConfigChangeKind::Session => {
+            require!(new_address != Pubkey::default(), ErrorCode::InvalidBotAddress);
+            let grant = session_grant.ok_or(ErrorCode::InvalidSessionExpiry)?;
+            require!(
+                grant.expires_slot > 0 || grant.expires_at > 0,
+                ErrorCode::InvalidSessionExpiry
+            );
+            return Ok(Some(grant));
+        }
+        // 费率在提议时按全局配置的最大费率校验
+        ConfigChangeKind::FeeRate => {}
+    }
+    Ok(None)
+}
 
-        msg!("验证通过，检查余额...");
-        
-        // 检查余额
-        let current_balance = get_token_balance(vault, ctx.accounts.mint.key());
-        msg!("当前余额: {}", current_balance);
-        msg!("需要取款金额: {}", amount);
-        
-        require!(current_balance >= amount, ErrorCode::InsufficientBalance);
+/// 内部函数：会话必须有过期条件，且尚未过期
+fn validate_session_expiry(grant: &SessionGrant, clock: &Clock) -> Result<()> {
+    require!(
+        grant.expires_slot > 0 || grant.expires_at > 0,
+        ErrorCode::InvalidSessionExpiry
+    );
+    require!(
+        grant.expires_slot == 0 || grant.expires_slot > clock.slot,
+        ErrorCode::InvalidSessionExpiry
+    );
+    require!(
+        grant.expires_at == 0 || grant.expires_at > clock.unix_timestamp,
+        ErrorCode::InvalidSessionExpiry
+    );
+    Ok(())
+}
 
-        msg!("余额充足，开始更新余额...");
-        
-        // 更新余额
-        set_token_balance(vault, ctx.accounts.mint.key(), current_balance - amount);
-        let new_balance = get_token_balance(vault, ctx.accounts.mint.key());
-        msg!("更新后余额: {}", new_balance);
+// 多签管理员最多的签名者数量 (审批记录为 u16 位图)
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
 
-        emit!(UserWithdrawEvent {
-            user: ctx.accounts.user.key(),
-            asset_metadata: ctx.accounts.mint.key(), // 对应 Aptos 的 Object<Metadata>
-            amount,
-            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
-        });
+/// 多签管理员账户 (每个金库一个 PDA)，创建后成为金库的管理员
+#[account]
+pub struct AdminMultisig {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 签名者列表
+    pub signers: Vec<Pubkey>,
+    /// 执行提案所需的审批数量
+    pub threshold: u8,
+    /// 签名者集合版本，每次更换签名者递增，旧版本的提案失效
+    pub signers_version: u32,
+    /// 已创建的提案数量 (用作下一个提案编号)
+    pub proposal_count: u64,
+    /// PDA bump
+    pub bump: u8,
+}
 
-        msg!("用户取款操作完成!");
-        Ok(())
+/// 多签可执行的管理员操作
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum MultisigAction {
+    /// 设置主机器人
+    SetBot { new_bot: Pubkey },
+    /// 提议新管理员 (仍需新管理员 accept_admin)
+    ProposeAdmin { new_admin: Pubkey },
+    /// 设置平台运营方 (Pubkey::default() 表示撤销)
+    SetOperator { new_operator: Pubkey },
+    /// 启用或暂停策略
+    SetStrategyEnabled { enabled: bool },
+    /// 更换签名者集合与阈值
+    SetSigners { signers: Vec<Pubkey>, threshold: u8 },
+    /// 提议受时间锁保护的配置变更 (启用时间锁后更换机器人、管理员等必须走此操作)
+    QueueConfigChange {
+        kind: ConfigChangeKind,
+        new_address: Pubkey,
+        session_grant: Option<SessionGrant>,
+    },
+    /// 接受管理员身份 (多签为 pending_admin 时)
+    AcceptAdmin,
+    /// 取消待定的管理员转移
+    CancelAdminTransfer,
+    /// 授予会话密钥 (仅未启用时间锁时，否则使用 QueueConfigChange)
+    CreateSession { session_key: Pubkey, grant: SessionGrant },
+    /// 设置机器人交易限制
+    SetTradeLimits {
+        max_trades_per_window: u32,
+        trade_window_slots: u64,
+        max_notional_per_trade: u64,
+        max_daily_notional: u64,
+    },
+    /// 设置预言机最小输出检查
+    SetOracleCheck { enabled: bool, max_slippage_bps: u16 },
+    /// 取消排队中的配置变更 (时间锁期间阻止已排队的变更)
+    CancelConfigChange { kind: ConfigChangeKind },
+    /// 撤销会话密钥
+    RevokeSession { session_key: Pubkey },
+}
+
+impl MultisigAction {
+    /// QueueConfigChange 操作的变更类型 (用于排队账户的 PDA 种子)
+    pub fn config_change_kind(&self) -> Option<ConfigChangeKind> {
+        match self {
+            MultisigAction::QueueConfigChange { kind, .. } => Some(*kind),
+            _ => None,
+        }
     }
 
-    /// 获取代币余额
-    pub fn get_balance(
-        ctx: Context<GetBalance>,
-        token: Pubkey,
-    ) -> Result<u64> {
-        let vault = &ctx.accounts.vault;
-        msg!("查询代币余额...");
-        msg!("代币地址: {}", token);
-        
-        let balance = get_token_balance(vault, token);
-        msg!("查询到的余额: {}", balance);
-        
-        Ok(balance)
+    /// CreateSession 操作的会话密钥 (用于会话账户的 PDA 种子)
+    pub fn session_key(&self) -> Option<&Pubkey> {
+        match self {
+            MultisigAction::CreateSession { session_key, .. } => Some(session_key),
+            _ => None,
+        }
     }
 
-    /// 发送交易信号并执行 DEX 交易 (对应 Aptos 的 send_trade_signal)
-    pub fn send_trade_signal(
-        ctx: Context<SendTradeSignal>,
-        token_in: Pubkey,
-        token_out: Pubkey,
-        amount_in: u64,
-        amount_out_minimum: u64,
-        fee_rate: u64, // 费率，按百万分之一为基本单位 (1 = 0.0001%)
-    ) -> Result<u64> {
-        msg!("开始发送交易信号操作...");
-        msg!("用户地址: {}", ctx.accounts.user.key());
-        msg!("输入代币: {}", token_in);
-        msg!("输出代币: {}", token_out);
-        msg!("输入金额: {}", amount_in);
-        msg!("最小输出金额: {}", amount_out_minimum);
-        msg!("费率: {} (百万分之一)", fee_rate);
-        
-        // 验证调用者是机器人
-        require!(ctx.accounts.user.key() == ctx.accounts.vault.bot, ErrorCode::OnlyBot);
-        require!(amount_in > 0, ErrorCode::InvalidAmount);
-        require!(fee_rate <= 1000000, ErrorCode::InvalidFeeRate); // 最大费率100%
-        require!(ctx.accounts.vault.is_initialized, ErrorCode::VaultNotInitialized);
-        
-        // 检查输入代币余额
-        let current_balance = get_token_balance(&ctx.accounts.vault, token_in);
-        require!(current_balance >= amount_in, ErrorCode::InsufficientBalance);
-        
-        msg!("验证通过，开始交换...");
-        msg!("当前输入代币余额: {}", current_balance);
-        
-        // 扣除输入代币
-        set_token_balance(&mut ctx.accounts.vault, token_in, current_balance - amount_in);
-        
-        // 直接调用 Byreal CLMM CPI 进行交换
-        let amount_out = execute_byreal_swap_cpi(&ctx, amount_in, amount_out_minimum)?;
-        
-        msg!("交换完成，输出金额: {}", amount_out);
-        require!(amount_out >= amount_out_minimum, ErrorCode::InsufficientOutputAmount);
-        
-        // 计算费用
-        let fee_amount = (amount_out * fee_rate) / 1000000;
-        let user_amount = amount_out - fee_amount;
-        
-        msg!("费用金额: {}", fee_amount);
-        msg!("用户获得金额: {}", user_amount);
-        
-        // 更新输出代币余额
-        let current_out_balance = get_token_balance(&ctx.accounts.vault, token_out);
-        set_token_balance(&mut ctx.accounts.vault, token_out, current_out_balance + user_amount);
-        
-        // 如果有费用，转账给费用接收者
-        if fee_amount > 0 && ctx.accounts.fee_recipient.key() != Pubkey::default() {
-            msg!("转账费用给接收者: {}", ctx.accounts.fee_recipient.key());
-            // 这里需要实现实际的费用转账逻辑
+    /// 检查要取消的排队变更和要撤销的会话与提案操作一致，其他操作不能传入这两个账户
+    pub fn check_target_accounts(
+        &self,
+        cancelled_change: Option<&QueuedConfigChange>,
+        revoked_session: Option<&BotSession>,
+    ) -> Result<()> {
+        match (self, cancelled_change, revoked_session) {
+            (MultisigAction::CancelConfigChange { kind }, Some(change), None) => {
+                require!(change.kind == *kind, ErrorCode::InvalidMultisigAccounts);
+            }
+            (MultisigAction::RevokeSession { session_key }, None, Some(session)) => {
+                require!(session.session_key == *session_key, ErrorCode::InvalidMultisigAccounts);
+            }
+            (MultisigAction::CancelConfigChange { .. } | MultisigAction::RevokeSession { .. }, _, _)
+            | (_, Some(_), _)
+            | (_, _, Some(_)) => return err!(ErrorCode::InvalidMultisigAccounts),
+            _ => {}
         }
-        
-        emit!(TradeSignalEvent {
-            user: ctx.accounts.user.key(),
-            from_asset_metadata: token_in, // 对应 Aptos 的 Object<Metadata>
-            to_asset_metadata: token_out,  // 对应 Aptos 的 Object<Metadata>
-            amount_in,
-            amount_out_min: amount_out_minimum,
-            amount_out,
-            fee_recipient: ctx.accounts.fee_recipient.key(),
-            fee_amount,
-            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
-        });
-        
-        msg!("交易信号发送完成!");
-        Ok(amount_out)
+        Ok(())
+    }
 }
 
-// ⚠️ Byreal CLMM 集成注意事项：
-// 1. 当前实现直接调用区块链上已部署的 Byreal CLMM 合约，不导入其代码库
-// 2. 使用正确的指令标识符和账户结构，确保与 Byreal CLMM 的 SwapSingle 结构匹配
-// 3. 需要验证的账户关系：
-//    - pool_state.amm_config == amm_config.key()
-//    - pool_state.observation_key == observation_state.key()
-//    - tick_array.pool_id == pool_state.key()
-//    - input_vault 和 output_vault 必须是池子的正确金库
-// 4. 指令标识符已从 Byreal CLMM IDL 中获取：
-//    - swap: [248, 198, 158, 145, 225, 117, 135, 200]
-//    - swap_v2: [43, 4, 237, 11, 26, 201, 30, 98]
-// 5. 池子地址计算需要包含 amm_config 参数
-// 6. 需要处理 remaining_accounts 中的 tick_array_bitmap_extension 和其他 tick arrays
-// 7. 实际部署时，确保使用正确的程序 ID（devnet/mainnet）
+/// 多签提案账户
+#[account]
+pub struct MultisigProposal {
+    /// 所属多签地址
+    pub multisig: Pubkey,
+    /// 提案编号
+    pub index: u64,
+    /// 提案操作
+    pub action: MultisigAction,
+    /// 审批位图 (第 i 位对应 signers[i])
+    pub approvals: u16,
+    /// 创建提案时的签名者集合版本
+    pub signers_version: u32,
+    /// 提议者地址
+    pub proposer: Pubkey,
+    /// PDA bump
+    pub bump: u8,
 }
 
-/// Byreal CLMM 交换函数
-fn byreal_swap_exact_input_single(
-    token_in: Pubkey,
-    token_out: Pubkey,
+/// 内部函数：校验多签签名者集合与阈值
+fn validate_multisig_signers(signers: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(
+        !signers.is_empty() && signers.len() <= MAX_MULTISIG_SIGNERS,
+        ErrorCode::InvalidMultisigSigners
+    );
+    require!(
+        threshold > 0 && threshold as usize <= signers.len(),
+        ErrorCode::InvalidMultisigThreshold
+    );
+    for (i, signer) in signers.iter().enumerate() {
+        require!(*signer != Pubkey::default(), ErrorCode::InvalidMultisigSigners);
+        require!(!signers[..i].contains(signer), ErrorCode::InvalidMultisigSigners);
+    }
+    Ok(())
+}
+
+/// 内部函数：查找签名者在多签中的位置
+fn multisig_signer_index(multisig: &AdminMultisig, key: &Pubkey) -> Result<usize> {
+    multisig
+        .signers
+        .iter()
+        .position(|signer| signer == key)
+        .ok_or_else(|| error!(ErrorCode::NotMultisigSigner))
+}
+
+/// 内部函数：记录签名者对提案的审批，返回当前审批数量
+fn record_multisig_approval(
+    multisig: &AdminMultisig,
+    proposal: &mut MultisigProposal,
+    approver: &Pubkey,
+) -> Result<u8> {
+    require!(
+        proposal.signers_version == multisig.signers_version,
+        ErrorCode::StaleMultisigProposal
+    );
+    let signer_index = multisig_signer_index(multisig, approver)?;
+    let bit = 1u16 << signer_index;
+    require!(proposal.approvals & bit == 0, ErrorCode::AlreadyApproved);
+
+    proposal.approvals |= bit;
+    Ok(proposal.approvals.count_ones() as u8)
+}
+
+/// 内部函数：检查提案可以执行 (签名者集合未更换且审批数量达到阈值)
+fn check_multisig_executable(multisig: &AdminMultisig, proposal: &MultisigProposal) -> Result<()> {
+    require!(
+        proposal.signers_version == multisig.signers_version,
+        ErrorCode::StaleMultisigProposal
+    );
+    require!(
+        proposal.approvals.count_ones() >= multisig.threshold as u32,
+        ErrorCode::MultisigThresholdNotMet
+    );
+    Ok(())
+}
+
+/// 限价单账户 (每个订单一个 PDA)：以不低于 min_amount_out / amount_in 的价格卖出 amount_in 个 token_in
+#[account]
+pub struct LimitOrder {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 订单编号 (由下单者指定，在金库内唯一)
+    pub order_id: u64,
+    /// 下单者地址 (投资者或机器人)，取消或成交后退还租金
+    pub placed_by: Pubkey,
+    /// 卖出代币
+    pub token_in: Pubkey,
+    /// 买入代币
+    pub token_out: Pubkey,
+    /// 卖出数量
+    pub amount_in: u64,
+    /// 最小买入数量 (即限价 P * 卖出数量)
+    pub min_amount_out: u64,
+    /// 过期时间戳 (秒)
+    pub expiry: i64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// 定投计划账户 (每个计划一个 PDA)：每隔 interval_seconds 用 amount_per_execution 个 token_in 买入 token_out，共 total_executions 次
+#[account]
+pub struct DcaSchedule {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 计划编号 (由投资者指定，在金库内唯一)
+    pub schedule_id: u64,
+    /// 创建者地址，取消或完成后退还租金
+    pub created_by: Pubkey,
+    /// 卖出代币
+    pub token_in: Pubkey,
+    /// 买入代币
+    pub token_out: Pubkey,
+    /// 每次卖出数量
+    pub amount_per_execution: u64,
+    /// 执行间隔 (秒)
+    pub interval_seconds: i64,
+    /// 总执行次数
+    pub total_executions: u32,
+    /// 已执行次数
+    pub executions_done: u32,
+    /// 下次可执行时间戳 (秒)
+    pub next_execution_at: i64,
+    /// 每次执行相对预言机隐含输出的最大滑点 (基点)
+    pub max_slippage_bps: u16,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// TWAP 订单账户 (每个订单一个 PDA)：把 total_amount_in 拆成每份 slice_amount 的子交换，
+/// 相邻子交换至少间隔 min_interval_slots 个 slot
+#[account]
+pub struct TwapOrder {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 订单编号 (由下单者指定，在金库内唯一)
+    pub order_id: u64,
+    /// 下单者地址 (投资者)，取消或完成后退还租金
+    pub placed_by: Pubkey,
+    /// 卖出代币
+    pub token_in: Pubkey,
+    /// 买入代币
+    pub token_out: Pubkey,
+    /// 总卖出数量
+    pub total_amount_in: u64,
+    /// 每个子交换的卖出数量
+    pub slice_amount: u64,
+    /// 相邻子交换的最小间隔 (slot)
+    pub min_interval_slots: u64,
+    /// 子交换相对预言机价格允许的最大滑点 (基点)
+    pub max_slippage_bps: u16,
+    /// 已卖出数量
+    pub amount_filled: u64,
+    /// 累计买入数量 (扣除费用前)
+    pub amount_out_total: u64,
+    /// 已执行的子交换数量
+    pub slices_executed: u32,
+    /// 上次执行子交换的 slot
+    pub last_execution_slot: u64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// 内部函数：校验 TWAP 订单参数
+fn validate_twap_order(
+    total_amount_in: u64,
+    slice_amount: u64,
+    min_interval_slots: u64,
+    max_slippage_bps: u16,
+) -> Result<()> {
+    require!(
+        slice_amount > 0 && slice_amount <= total_amount_in,
+        ErrorCode::InvalidTwapOrder
+    );
+    // 间隔为 0 时 keeper 可以在同一 slot 甚至同一笔交易内执行所有子交换，失去拆单的意义
+    require!(min_interval_slots > 0, ErrorCode::InvalidTwapOrder);
+    validate_order_slippage_bps(max_slippage_bps)
+}
+
+/// 目标权重中最多可配置的代币数量
+pub const MAX_TARGET_WEIGHTS: usize = 8;
+
+/// 单个代币的目标权重
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct TargetWeight {
+    /// 代币地址
+    pub mint: Pubkey,
+    /// 目标权重 (基点)
+    pub weight_bps: u16,
+}
+
+/// 再平衡目标账户 (每个金库一个 PDA)，由投资者设置各代币的目标权重
+#[account]
+pub struct RebalanceTargets {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 估值使用的基础资产
+    pub base_mint: Pubkey,
+    /// 容忍带宽：任一代币的权重偏离目标超过该值 (基点) 时才允许再平衡
+    pub tolerance_bps: u16,
+    /// 再平衡交易相对预言机隐含输出的最大滑点 (基点)
+    pub max_slippage_bps: u16,
+    /// 目标权重列表，权重之和为 10000，未列出的代币目标权重为 0
+    pub targets: Vec<TargetWeight>,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// 条件单类型
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConditionalOrderKind {
+    /// 止损：价格跌至触发价及以下时卖出
+    StopLoss,
+    /// 止盈：价格涨至触发价及以上时卖出
+    TakeProfit,
+    /// 追踪止损：价格从链上记录的最高价回落 trailing_bps 时卖出
+    TrailingStop,
+}
+
+/// 条件单账户 (每个订单一个 PDA)，由预言机价格触发
+/// 价格均为以 token_out 计价的 token_in 价格 (按完整代币计，10^18 精度)
+#[account]
+pub struct ConditionalOrder {
+    /// 所属金库地址
+    pub vault: Pubkey,
+    /// 订单编号 (由下单者指定，在金库内唯一)
+    pub order_id: u64,
+    /// 下单者地址 (投资者)，取消或成交后退还租金
+    pub placed_by: Pubkey,
+    /// 条件单类型
+    pub kind: ConditionalOrderKind,
+    /// 卖出代币 (持仓)
+    pub token_in: Pubkey,
+    /// 买入代币 (退出资产)
+    pub token_out: Pubkey,
+    /// 卖出数量
+    pub amount_in: u64,
+    /// 触发价 (止损 / 止盈)
+    pub trigger_price: u128,
+    /// 追踪止损的回落比例 (基点)
+    pub trailing_bps: u16,
+    /// 追踪止损记录的最高价
+    pub peak_price: u128,
+    /// 过期时间戳 (秒，0 表示不过期)
+    pub expiry: i64,
+    /// 触发时相对预言机隐含输出的最大滑点 (基点)
+    pub max_slippage_bps: u16,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// 内部函数：要求地址持有任一指定角色
+fn require_vault_role(vault: &PersonalVault, key: Pubkey, roles: &[VaultRole]) -> Result<()> {
+    require!(
+        roles.iter().any(|role| vault.has_role(*role, &key)),
+        ErrorCode::Unauthorized
+    );
+    Ok(())
+}
+
+/// 全局配置账户结构 (单例 PDA，由平台管理员控制)
+#[account]
+pub struct Config {
+    /// 平台管理员地址
+    pub authority: Pubkey,
+    /// 是否暂停所有金库的交易
+    pub paused: bool,
+    /// 是否同时暂停所有金库的存款
+    pub deposits_paused: bool,
+    /// PDA bump
+    pub bump: u8,
+    /// 平台允许设置的最大交易费率 (百万分之一)
+    pub max_fee_rate: u64,
+    /// 每笔交易收取的费率 (百万分之一)
+    pub default_fee_rate: u64,
+    /// 平台金库 (费用接收者) 地址
+    pub treasury: Pubkey,
+    /// 排队中的费率提高 (到期后通过 execute_fee_config_change 生效)
+    pub pending_fee_config: Option<PendingFeeConfig>,
+}
+
+/// 排队中的费率配置
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PendingFeeConfig {
+    /// 新的最大费率 (百万分之一)
+    pub max_fee_rate: u64,
+    /// 新的默认费率 (百万分之一)
+    pub default_fee_rate: u64,
+    /// 可执行时间戳 (秒)
+    pub eta: i64,
+}
+
+// 提高全局费率配置的延迟，不短于金库时间锁上限，所有金库的投资者都有时间在费率提高前退出
+pub const FEE_CONFIG_INCREASE_DELAY_SECONDS: i64 = MAX_TIMELOCK_SECONDS;
+
+// 费率分母，按百万分之一为基本单位 (1 = 0.0001%)
+pub const FEE_RATE_DENOMINATOR: u64 = 1_000_000;
+
+// 协议硬编码的费率上限 (10%)，max_fee_rate 不能超过该值
+pub const PROTOCOL_FEE_RATE_CAP: u64 = 100_000;
+
+/// 内部函数：校验费率配置
+fn validate_fee_config(max_fee_rate: u64, default_fee_rate: u64, treasury: Pubkey) -> Result<()> {
+    require!(max_fee_rate <= PROTOCOL_FEE_RATE_CAP, ErrorCode::InvalidFeeRate);
+    require!(default_fee_rate <= max_fee_rate, ErrorCode::InvalidFeeRate);
+    require!(treasury != Pubkey::default(), ErrorCode::InvalidTreasury);
+    Ok(())
+}
+
+/// 内部函数：金库实际使用的交易费率，优先使用金库费率，否则使用全局默认费率
+fn vault_fee_rate(vault: &PersonalVault, config: &Config) -> Result<u64> {
+    // 平台调低最大费率后，已设置的金库费率按最大费率收取，避免所有交易失败
+    let fee_rate = match vault.fee_rate_override {
+        Some(fee_rate) => fee_rate.min(config.max_fee_rate),
+        None => config.default_fee_rate,
+    };
+    require!(fee_rate <= config.max_fee_rate, ErrorCode::InvalidFeeRate);
+    Ok(fee_rate)
+}
+
+/// 内部函数：校验平台管理员直接设置的金库费率
+/// 启用时间锁的金库只能立即降低实际费率，提高费率需通过 queue_config_change (FeeRate) 排队
+fn check_vault_fee_rate_change(vault: &PersonalVault, config: &Config, fee_rate: Option<u64>) -> Result<()> {
+    if let Some(fee_rate) = fee_rate {
+        require!(fee_rate <= config.max_fee_rate, ErrorCode::InvalidFeeRate);
+    }
+    let current_fee_rate = vault_fee_rate(vault, config)?;
+    let new_fee_rate = fee_rate.unwrap_or(config.default_fee_rate);
+    msg!("实际费率从 {} 变为 {} (百万分之一)", current_fee_rate, new_fee_rate);
+    require!(
+        vault.timelock_seconds == 0 || new_fee_rate <= current_fee_rate,
+        ErrorCode::TimelockEnabled
+    );
+    Ok(())
+}
+
+// 每日名义金额统计窗口长度 (秒)
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// 内部函数：检查并记录交易限制
+fn check_and_record_trade_limits(
+    limits: &mut TradeLimits,
     amount_in: u64,
-    amount_out_minimum: u64,
-) -> Result<u64> {
-    msg!("开始 Byreal CLMM 交换...");
-    msg!("输入代币: {}", token_in);
-    msg!("输出代币: {}", token_out);
-    msg!("输入金额: {}", amount_in);
-    msg!("最小输出金额: {}", amount_out_minimum);
-    
-    // Byreal CLMM 使用池子地址而不是交易对
-    // 需要先获取或计算池子地址
-    // 这里需要传入 amm_config，暂时使用默认值
-    let amm_config = Pubkey::default(); // 实际使用时需要传入正确的 amm_config
-    let pool_address = get_byreal_pool_address(amm_config, token_in, token_out)?;
-    msg!("Byreal 池子地址: {}", pool_address);
-    
-    // 执行 CLMM 交换
-    let amount_out = execute_byreal_clmm_swap(
-        pool_address,
-        token_in,
-        token_out,
-        amount_in,
-        amount_out_minimum,
-    )?;
-    
-    msg!("Byreal CLMM 交换完成，输出金额: {}", amount_out);
-    Ok(amount_out)
+    slot: u64,
+    timestamp: i64,
+) -> Result<()> {
+    msg!("检查交易限制...");
+
+    // 单笔名义金额
+    if limits.max_notional_per_trade > 0 {
+        require!(amount_in <= limits.max_notional_per_trade, ErrorCode::TradeNotionalExceeded);
+    }
+
+    // 交易次数窗口
+    if limits.max_trades_per_window > 0 {
+        if slot.saturating_sub(limits.window_start_slot) >= limits.trade_window_slots {
+            msg!("交易次数窗口重置，起始 slot: {}", slot);
+            limits.window_start_slot = slot;
+            limits.trades_in_window = 0;
+        }
+        require!(
+            limits.trades_in_window < limits.max_trades_per_window,
+            ErrorCode::TradeRateLimitExceeded
+        );
+        limits.trades_in_window += 1;
+        msg!("当前窗口交易次数: {}", limits.trades_in_window);
+    }
+
+    // 每日名义金额窗口
+    if limits.max_daily_notional > 0 {
+        if timestamp.saturating_sub(limits.day_start_timestamp) >= SECONDS_PER_DAY {
+            msg!("每日窗口重置，起始时间戳: {}", timestamp);
+            limits.day_start_timestamp = timestamp;
+            limits.daily_notional = 0;
+        }
+        let new_daily_notional = limits
+            .daily_notional
+            .checked_add(amount_in)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(new_daily_notional <= limits.max_daily_notional, ErrorCode::DailyNotionalExceeded);
+        limits.daily_notional = new_daily_notional;
+        msg!("当日累计名义金额: {}", limits.daily_notional);
+    }
+
+    Ok(())
 }
 
-/// 获取 Byreal 池子地址
-fn get_byreal_pool_address(amm_config: Pubkey, token_a: Pubkey, token_b: Pubkey) -> Result<Pubkey> {
-    msg!("获取 Byreal 池子地址: {} <-> {}", token_a, token_b);
-    
-    // 在 Byreal CLMM 中，池子地址是通过确定性方式计算的
-    // 根据 Byreal 源码，池子地址的种子是 [b"pool", amm_config, token_mint_0, token_mint_1]
-    // 其中 token_mint_0 和 token_mint_1 按地址排序
-    
-    let (token_mint_0, token_mint_1) = if token_a < token_b {
-        (token_a, token_b)
+/// 内部函数：检查并记录一笔机器人交易的金库限制和机器人自身的限制 (主机器人只受金库限制)
+fn check_and_record_bot_trade(
+    vault: &mut PersonalVault,
+    bot: Pubkey,
+    amount_in: u64,
+    slot: u64,
+    timestamp: i64,
+) -> Result<()> {
+    check_and_record_trade_limits(&mut vault.trade_limits, amount_in, slot, timestamp)?;
+    if let Some(entry) = vault.bots.iter_mut().find(|entry| entry.key == bot) {
+        msg!("检查机器人 {} 的单独限制", bot);
+        check_and_record_trade_limits(&mut entry.limits, amount_in, slot, timestamp)?;
+    }
+    Ok(())
+}
+
+// 预言机注册表最多登记的价格源数量
+pub const MAX_PRICE_FEEDS: usize = 32;
+
+/// 价格源类型
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PriceSource {
+    /// Pyth 拉取式预言机
+    Pyth,
+    /// Switchboard On-Demand
+    Switchboard,
+    /// 本程序的管理员喂价 (对应 PriceOracle.sol)
+    AdminOracle,
+    /// Byreal CLMM 池子观察账户的 TWAP
+    ClmmTwap,
+}
+
+/// 代币到价格源的映射，同一代币可为每种价格源各配置一项
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PriceFeedEntry {
+    /// 代币地址
+    pub mint: Pubkey,
+    /// 价格源类型
+    pub source: PriceSource,
+    /// Pyth 为价格源 ID，Switchboard 为 feed 账户地址，
+    /// AdminOracle 为 (代币, 计价代币) 的 TokenPairPrice 账户地址，
+    /// ClmmTwap 为 (代币, 美元稳定币) 的 Byreal 池子地址
+    pub feed_id: [u8; 32],
+}
+
+/// 预言机注册表账户结构 (单例 PDA，由平台管理员维护)
+#[account]
+pub struct OracleRegistry {
+    /// 价格最大过期时间 (秒)
+    pub max_staleness_seconds: u64,
+    /// 置信区间相对价格的最大比例 (基点)
+    pub max_confidence_bps: u16,
+    /// PDA bump
+    pub bump: u8,
+    /// 代币价格源列表
+    pub feeds: Vec<PriceFeedEntry>,
+    /// 喂价角色地址 (对应 PriceOracle 的 ORACLE_ROLE)
+    pub oracle_authority: Pubkey,
+    /// 管理员喂价的最大过期时间 (秒，对应 PriceOracle 的 maxPriceAge)
+    pub max_price_age: u64,
+    /// CLMM TWAP 时间窗口 (秒)
+    pub twap_window_seconds: u32,
+    /// 价格源之间相对中位数的最大偏离 (基点)
+    pub max_deviation_bps: u16,
+    /// 聚合价格所需的最少可用价格源数量
+    pub min_price_sources: u8,
+}
+
+// 管理员喂价的价格精度 (对应 PriceOracle 的 10^18)
+pub const PRICE_PRECISION: u128 = 1_000_000_000_000_000_000;
+
+// 管理员喂价默认最大过期时间 (1 小时)
+pub const DEFAULT_MAX_PRICE_AGE: u64 = 3_600;
+
+// 默认 CLMM TWAP 时间窗口 (10 分钟)
+pub const DEFAULT_TWAP_WINDOW_SECONDS: u32 = 600;
+
+// 默认价格源最大偏离 (5%)
+pub const DEFAULT_MAX_DEVIATION_BPS: u16 = 500;
+
+/// 交易对价格账户结构 (对应 PriceOracle 的 prices / lastUpdateTimestamp)
+/// 1 个 token_a 等于 price / 10^18 个 token_b (按完整代币计)
+#[account]
+pub struct TokenPairPrice {
+    /// 代币 A 地址
+    pub token_a: Pubkey,
+    /// 代币 B 地址
+    pub token_b: Pubkey,
+    /// 价格 (乘以 10^18)
+    pub price: u128,
+    /// 最后更新时间戳 (秒)
+    pub last_update_timestamp: i64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// 内部函数：写入交易对价格并发出事件
+fn write_pair_price(
+    pair: &mut TokenPairPrice,
+    token_a: Pubkey,
+    token_b: Pubkey,
+    price: u128,
+    timestamp: i64,
+) {
+    pair.token_a = token_a;
+    pair.token_b = token_b;
+    pair.price = price;
+    pair.last_update_timestamp = timestamp;
+
+    msg!("价格更新: {} -> {} = {}", token_a, token_b, price);
+
+    emit!(PriceUpdatedEvent {
+        token_a,
+        token_b,
+        price,
+        timestamp_microseconds: timestamp as u64 * 1_000_000, // 转换为微秒
+    });
+}
+
+/// 内部函数：计算反向价格 (10^36 / price)
+fn reverse_pair_price(price: u128) -> Result<u128> {
+    require!(price > 0, ErrorCode::InvalidOraclePrice);
+    let reverse_price = PRICE_PRECISION * PRICE_PRECISION / price;
+    require!(reverse_price > 0, ErrorCode::InvalidOraclePrice);
+    Ok(reverse_price)
+}
+
+/// 内部函数：读取未过期的交易对价格 (对应 PriceOracle 的 getPrice)
+fn get_fresh_pair_price(pair: &TokenPairPrice, max_price_age: u64, now: i64) -> Result<u128> {
+    require!(pair.price > 0, ErrorCode::InvalidOraclePrice);
+    let age = now.saturating_sub(pair.last_update_timestamp);
+    require!(age >= 0 && (age as u64) <= max_price_age, ErrorCode::StalePrice);
+    Ok(pair.price)
+}
+
+/// 内部函数：从池子状态中获取输入和输出代币的精度
+fn get_pool_decimals(pool: &PoolState, token_in: Pubkey, token_out: Pubkey) -> Result<(u8, u8)> {
+    let token_mint_0 = pool.token_mint_0;
+    let token_mint_1 = pool.token_mint_1;
+    if token_in == token_mint_0 && token_out == token_mint_1 {
+        Ok((pool.mint_decimals_0, pool.mint_decimals_1))
+    } else if token_in == token_mint_1 && token_out == token_mint_0 {
+        Ok((pool.mint_decimals_1, pool.mint_decimals_0))
     } else {
-        (token_b, token_a)
+        err!(ErrorCode::InvalidSwapAccounts)
+    }
+}
+
+/// 内部函数：按池子现价计算 token_in 兑换 token_out 的输出金额
+fn pool_spot_amount_out(pool: &PoolState, token_in: Pubkey, token_out: Pubkey, amount_in: u64) -> Result<u64> {
+    let token_mint_0 = pool.token_mint_0;
+    let token_mint_1 = pool.token_mint_1;
+    let zero_for_one = if token_in == token_mint_0 && token_out == token_mint_1 {
+        true
+    } else if token_in == token_mint_1 && token_out == token_mint_0 {
+        false
+    } else {
+        return err!(ErrorCode::InvalidSwapAccounts);
     };
-    
-    let (pool_address, _bump) = Pubkey::find_program_address(
-        &[
-            b"pool",
-            amm_config.as_ref(),
-            token_mint_0.as_ref(),
-            token_mint_1.as_ref(),
-        ],
-        &Pubkey::from_str(BYREAL_CLMM_PROGRAM_ID).unwrap(),
-    );
-    
-    msg!("计算得到的池子地址: {}", pool_address);
-    Ok(pool_address)
+    clmm_twap::spot_amount_out(pool.sqrt_price_x64, amount_in, zero_for_one)
+        .ok_or_else(|| error!(ErrorCode::InvalidPoolPrice))
 }
 
-/// 执行 Byreal CLMM 交换
-fn execute_byreal_clmm_swap(
-    pool_address: Pubkey,
+/// 内部函数：按预言机价格计算交换的最小输出 (隐含输出扣除 slippage_bps)，代币精度从池子读取
+fn oracle_amount_out_minimum<'info>(
+    pool_state: &'info AccountInfo<'info>,
     token_in: Pubkey,
     token_out: Pubkey,
     amount_in: u64,
-    amount_out_minimum: u64,
+    price_in: &OraclePrice,
+    price_out: &OraclePrice,
+    slippage_bps: u16,
 ) -> Result<u64> {
-    msg!("执行 Byreal CLMM 交换...");
-    msg!("池子地址: {}", pool_address);
-    msg!("输入代币: {}", token_in);
-    msg!("输出代币: {}", token_out);
-    msg!("输入金额: {}", amount_in);
-    msg!("最小输出金额: {}", amount_out_minimum);
-    
-    // 构建 Byreal CLMM 交换指令
-          let swap_instruction = build_byreal_clmm_swap_instruction(
-          amount_in,
-          amount_out_minimum,
-          0, // sqrt_price_limit_x64: 0 表示无限制
-          true, // is_base_input: true 表示 base input 模式
-      )?;
-    
-    // 执行交换指令
-    // 直接返回模拟的输出金额
-    let amount_out = amount_in * 99 / 100; // 模拟 1% 滑点
-    
-    msg!("Byreal CLMM 交换执行完成，输出: {}", amount_out);
-    Ok(amount_out)
+    let (decimals_in, decimals_out) = {
+        let pool_state = AccountLoader::<PoolState>::try_from(pool_state)?;
+        let pool = pool_state.load()?;
+        get_pool_decimals(&pool, token_in, token_out)?
+    };
+    let implied_amount_out =
+        oracle::implied_amount_out(amount_in, price_in, decimals_in, price_out, decimals_out)
+            .ok_or(ErrorCode::MathOverflow)?;
+    let amount_out_minimum = oracle::apply_slippage_bps(implied_amount_out, slippage_bps);
+
+    msg!("预言机隐含输出: {}，最小输出: {}", implied_amount_out, amount_out_minimum);
+    Ok(amount_out_minimum)
 }
 
-/// 构建 Byreal CLMM 交换指令
-fn build_byreal_clmm_swap_instruction(
-    amount: u64,
-    other_amount_threshold: u64,
-    sqrt_price_limit_x64: u128,
-    is_base_input: bool,
-) -> Result<Vec<u8>> {
-    msg!("构建 Byreal CLMM 交换指令...");
-    
-    // 根据 Byreal CLMM 的实际指令格式构建
-    // 参考 byreal-clmm/programs/amm/src/instructions/swap.rs 中的 swap 函数参数
+/// 内部函数：校验 keeper 执行订单时使用的滑点 (基点)
+/// 为 0 时预言机隐含输出几乎不可能满足，订单永远无法成交；为 10000 时最小输出为 0，任何 keeper 都可以夹击整笔交易
+fn validate_order_slippage_bps(max_slippage_bps: u16) -> Result<()> {
+    require!(
+        max_slippage_bps > 0 && (max_slippage_bps as u64) < oracle::BPS_DENOMINATOR,
+        ErrorCode::InvalidSlippageBps
+    );
+    Ok(())
+}
+
+/// 内部函数：在 price_accounts 中按地址查找账户
+fn find_price_account<'a, 'info>(
+    price_accounts: &'a [AccountInfo<'info>],
+    key: Pubkey,
+) -> Result<&'a AccountInfo<'info>> {
+    price_accounts
+        .iter()
+        .find(|account| account.key() == key)
+        .ok_or_else(|| error!(ErrorCode::PriceAccountMissing))
+}
+
+/// 内部函数：读取单个价格源的价格，并检查时效性与置信区间
+fn load_source_price(
+    registry: &OracleRegistry,
+    entry: &PriceFeedEntry,
+    price_accounts: &[AccountInfo],
+    now: i64,
+) -> Result<OraclePrice> {
+    let mint = entry.mint;
+    let price = match entry.source {
+        PriceSource::Pyth => price_accounts
+            .iter()
+            .filter(|account| *account.owner == pyth::PYTH_RECEIVER_PROGRAM_ID)
+            .find_map(|account| pyth::load_price(account, &entry.feed_id).ok())
+            .ok_or(ErrorCode::PriceAccountMissing)?,
+        PriceSource::Switchboard => {
+            let account = find_price_account(price_accounts, Pubkey::new_from_array(entry.feed_id))?;
+            switchboard::load_price(account)?
+        }
+        PriceSource::AdminOracle => {
+            let account = find_price_account(price_accounts, Pubkey::new_from_array(entry.feed_id))?;
+            require!(*account.owner == crate::ID, ErrorCode::InvalidPriceAccount);
+            let pair = TokenPairPrice::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+            require!(pair.token_a == mint, ErrorCode::PriceFeedMismatch);
+            oracle::from_precision_price(pair.price, pair.last_update_timestamp)
+                .ok_or(ErrorCode::InvalidOraclePrice)?
+        }
+        PriceSource::ClmmTwap => {
+            let byreal_program_id = Pubkey::from_str(BYREAL_CLMM_PROGRAM_ID).unwrap();
+            let pool_id = Pubkey::new_from_array(entry.feed_id);
+            let pool_account = find_price_account(price_accounts, pool_id)?;
+            require!(*pool_account.owner == byreal_program_id, ErrorCode::InvalidPriceAccount);
+            let pool = clmm_twap::parse_pool(&pool_account.try_borrow_data()?)?;
+
+            let observation_account = find_price_account(price_accounts, pool.observation_key)?;
+            require!(*observation_account.owner == byreal_program_id, ErrorCode::InvalidPriceAccount);
+            clmm_twap::load_price(
+                &pool_id,
+                &pool,
+                &observation_account.try_borrow_data()?,
+                &mint,
+                registry.twap_window_seconds,
+            )?
+        }
+    };
+
+    let max_staleness_seconds = match entry.source {
+        PriceSource::AdminOracle => registry.max_price_age,
+        _ => registry.max_staleness_seconds,
+    };
+    oracle::validate_price(
+        &price,
+        now,
+        max_staleness_seconds,
+        registry.max_confidence_bps,
+    )?;
+    Ok(price)
+}
+
+/// 内部函数：读取代币的预言机价格
+/// 读取注册表为该代币配置的所有价格源 (账户通过 price_accounts 传入)，
+/// 取中位数并拒绝相互偏离过大的价格
+/// 调用者必须传入每个已配置价格源的账户，防止只挑选有利的价格源；
+/// 账户存在但价格过期或置信区间过宽的价格源会被跳过，并计入 min_price_sources 检查
+fn get_oracle_price(
+    registry: &OracleRegistry,
+    mint: Pubkey,
+    price_accounts: &[AccountInfo],
+    now: i64,
+) -> Result<OraclePrice> {
+    msg!("读取预言机价格，代币地址: {}", mint);
+
+    let mut configured = 0;
+    let mut prices = Vec::new();
+    for entry in registry.feeds.iter().filter(|entry| entry.mint == mint) {
+        configured += 1;
+        match load_source_price(registry, entry, price_accounts, now) {
+            Ok(price) => {
+                msg!("价格源 {:?}: {} 指数: {}", entry.source, price.price, price.expo);
+                prices.push(price);
+            }
+            Err(error) if error == ErrorCode::PriceAccountMissing.into() => {
+                msg!("缺少价格源 {:?} 的账户", entry.source);
+                return Err(error);
+            }
+            Err(error) => msg!("价格源 {:?} 不可用: {}", entry.source, error),
+        }
+    }
+
+    require!(configured > 0, ErrorCode::PriceFeedNotConfigured);
+    require!(
+        prices.len() >= registry.min_price_sources.max(1) as usize,
+        ErrorCode::OraclePriceUnavailable
+    );
+
+    let price = oracle::aggregate_median(&prices, registry.max_deviation_bps)?;
+    msg!("聚合价格: {} 指数: {} 可用价格源: {}", price.price, price.expo, prices.len());
+    Ok(price)
+}
+
+/// 单个代币的估值 (对应 UniswapVault 的 getPortfolioComposition)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TokenValuation {
+    /// 代币地址
+    pub token: Pubkey,
+    /// 持仓数量
+    pub amount: u64,
+    /// 以基础资产最小单位计的价值
+    pub value: u64,
+    /// 占总资产的权重 (基点)
+    pub weight_bps: u16,
+}
+
+/// 投资组合构成与净值
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PortfolioComposition {
+    /// 计价的基础资产
+    pub base_mint: Pubkey,
+    /// 总资产净值 (对应 UniswapVault 的 totalAssets)
+    pub total_value: u64,
+    /// 各代币估值，仅包含余额大于 0 的代币
+    pub tokens: Vec<TokenValuation>,
+}
+
+/// 内部函数：读取代币精度，代币账户通过 remaining_accounts 传入
+fn get_mint_decimals(mint: Pubkey, accounts: &[AccountInfo]) -> Result<u8> {
+    let account = accounts
+        .iter()
+        .find(|account| account.key() == mint)
+        .ok_or(ErrorCode::MintAccountMissing)?;
+    require!(*account.owner == anchor_spl::token::ID, ErrorCode::MintAccountMissing);
+    let mint_account = Mint::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    Ok(mint_account.decimals)
+}
+
+/// 内部函数：按预言机价格对金库所有持仓估值，以 base_mint 计价
+/// remaining_accounts 需包含各代币的价格账户与代币 (Mint) 账户
+fn value_portfolio(
+    vault: &PersonalVault,
+    registry: &OracleRegistry,
+    base_mint: Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<PortfolioComposition> {
+    msg!("开始计算投资组合估值...");
+    msg!("基础资产: {}", base_mint);
+
+    let now = Clock::get()?.unix_timestamp;
+    let positions: Vec<&TokenBalance> = vault
+        .balances
+        .iter()
+        .filter(|balance| balance.amount > 0)
+        .collect();
+
+    // 只有持有非基础资产时才需要基础资产的价格
+    let base_price = if positions.iter().any(|balance| balance.token != base_mint) {
+        Some((
+            get_oracle_price(registry, base_mint, accounts, now)?,
+            get_mint_decimals(base_mint, accounts)?,
+        ))
+    } else {
+        None
+    };
+
+    let mut tokens = Vec::with_capacity(positions.len());
+    let mut total_value: u64 = 0;
+    for balance in positions {
+        let value = match &base_price {
+            Some((base_price, base_decimals)) if balance.token != base_mint => {
+                let price = get_oracle_price(registry, balance.token, accounts, now)?;
+                let decimals = get_mint_decimals(balance.token, accounts)?;
+                oracle::implied_amount_out(balance.amount, &price, decimals, base_price, *base_decimals)
+                    .ok_or(ErrorCode::MathOverflow)?
+            }
+            _ => balance.amount,
+        };
+        msg!("代币: {} 数量: {} 价值: {}", balance.token, balance.amount, value);
+
+        total_value = total_value.checked_add(value).ok_or(ErrorCode::MathOverflow)?;
+        tokens.push(TokenValuation {
+            token: balance.token,
+            amount: balance.amount,
+            value,
+            weight_bps: 0,
+        });
+    }
+
+    if total_value > 0 {
+        for token in tokens.iter_mut() {
+            token.weight_bps =
+                ((token.value as u128) * (oracle::BPS_DENOMINATOR as u128) / (total_value as u128)) as u16;
+        }
+    }
+
+    msg!("总资产净值: {}", total_value);
+    Ok(PortfolioComposition {
+        base_mint,
+        total_value,
+        tokens,
+    })
+}
+
+/// 内部函数：检查机器人给出的最小输出不低于预言机隐含输出扣除滑点容忍度
+#[allow(clippy::too_many_arguments)]
+fn check_oracle_min_out(
+    token_in: Pubkey,
+    token_out: Pubkey,
+    decimals_in: u8,
+    decimals_out: u8,
+    amount_in: u64,
+    amount_out_minimum: u64,
+    max_slippage_bps: u16,
+    registry: &OracleRegistry,
+    price_accounts: &[AccountInfo],
+) -> Result<()> {
+    msg!("检查预言机最小输出...");
+
+    let now = Clock::get()?.unix_timestamp;
+    let price_in = get_oracle_price(registry, token_in, price_accounts, now)?;
+    let price_out = get_oracle_price(registry, token_out, price_accounts, now)?;
+
+    let implied_amount_out = oracle::implied_amount_out(
+        amount_in,
+        &price_in,
+        decimals_in,
+        &price_out,
+        decimals_out,
+    )
+    .ok_or(ErrorCode::MathOverflow)?;
+    let oracle_minimum = oracle::apply_slippage_bps(implied_amount_out, max_slippage_bps);
+
+    msg!("预言机隐含输出: {}", implied_amount_out);
+    msg!("允许的最小输出: {}", oracle_minimum);
+
+    require!(amount_out_minimum >= oracle_minimum, ErrorCode::MinimumOutputBelowOracle);
+    Ok(())
+}
+
+/// 内部函数：按费率拆分交换输出，返回 (记入金库的金额, 费用金额)
+fn split_trade_fee(amount_out: u64, fee_rate: u64) -> Result<(u64, u64)> {
+    let fee_amount = (amount_out as u128)
+        .checked_mul(fee_rate as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / FEE_RATE_DENOMINATOR as u128;
+    let fee_amount = u64::try_from(fee_amount).map_err(|_| ErrorCode::MathOverflow)?;
+    let user_amount = amount_out.checked_sub(fee_amount).ok_or(ErrorCode::MathOverflow)?;
+    Ok((user_amount, fee_amount))
+}
+
+/// 内部函数：把交换输出中的费用从输出代币账户转入平台金库的关联代币账户
+/// 输出代币账户由交换的签名者持有，因此由签名者授权转账
+fn transfer_trade_fee<'info>(
+    token_program: &AccountInfo<'info>,
+    output_token_account: &AccountInfo<'info>,
+    fee_recipient: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    fee_amount: u64,
+) -> Result<()> {
+    if fee_amount == 0 {
+        return Ok(());
+    }
+    msg!("转账费用给接收者: {}，金额: {}", fee_recipient.key(), fee_amount);
+    token::transfer(
+        CpiContext::new(
+            token_program.clone(),
+            Transfer {
+                from: output_token_account.clone(),
+                to: fee_recipient.clone(),
+                authority: authority.clone(),
+            },
+        ),
+        fee_amount,
+    )
+}
+
+/// 内部函数：获取代币余额
+fn get_token_balance(vault: &PersonalVault, token: Pubkey) -> u64 {
+    msg!("查找代币余额，代币地址: {}", token);
+    msg!("当前金库中代币数量: {}", vault.balances.len());
     
-    let mut instruction_data = Vec::new();
+    for (index, balance) in vault.balances.iter().enumerate() {
+        msg!("检查第{}个代币: {}", index + 1, balance.token);
+        if balance.token == token {
+            msg!("找到代币，余额: {}", balance.amount);
+            return balance.amount;
+        }
+    }
     
-    // 指令标识符 - 使用正确的 discriminator
-    instruction_data.extend_from_slice(&BYREAL_SWAP_INSTRUCTION_DISCRIMINATOR);
+    msg!("未找到代币，返回0");
+    0
+}
+
+/// 内部函数：设置代币余额
+fn set_token_balance(vault: &mut PersonalVault, token: Pubkey, amount: u64) {
+    msg!("设置代币余额，代币地址: {}, 新余额: {}", token, amount);
     
-    // 添加参数 - 根据 Byreal CLMM 的 swap 函数参数
-    instruction_data.extend_from_slice(&amount.to_le_bytes()); // amount: u64
-    instruction_data.extend_from_slice(&other_amount_threshold.to_le_bytes()); // other_amount_threshold: u64
-    instruction_data.extend_from_slice(&sqrt_price_limit_x64.to_le_bytes()); // sqrt_price_limit_x64: u128
-    instruction_data.push(if is_base_input { 1u8 } else { 0u8 }); // is_base_input: bool
+    for (index, balance) in vault.balances.iter_mut().enumerate() {
+        if balance.token == token {
+            msg!("更新现有代币余额，索引: {}", index);
+            let old_amount = balance.amount;
+            balance.amount = amount;
+            msg!("余额从 {} 更新为 {}", old_amount, amount);
+            return;
+        }
+    }
     
-    msg!("Byreal CLMM 交换指令构建完成");
-    Ok(instruction_data)
+    // 如果代币不存在，添加新条目
+    msg!("代币不存在，添加新代币到余额列表");
+    vault.balances.push(TokenBalance {
+        token,
+        amount,
+    });
+    msg!("新代币已添加，当前代币总数: {}", vault.balances.len());
+}
+
+/// 个人金库程序
+/// 直接管理用户的个人金库，包含存款、取款、交换等功能
+#[program]
+pub mod personal_vault {
+    use super::*;
+
+    /// 创建余额管理器 (对应 Aptos 的 create_balance_manager)
+    pub fn create_balance_manager(
+        ctx: Context<CreateBalanceManager>,
+        bot_address: Pubkey,      // 机器人合约地址，用于执行自动交易
+        swap_router: Pubkey,      // DEX 路由器地址（如 Raydium、Orca）
+        wrapped_native: Pubkey,   // 包装 SOL 代币地址
+        vault_admin: Pubkey,      // 金库管理员地址
+        operator: Pubkey,         // 平台运营方地址，Pubkey::default() 表示不设置
+    ) -> Result<()> {
+        msg!("开始创建余额管理器...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("管理员地址: {}", vault_admin);
+        msg!("运营方地址: {}", operator);
+        msg!("机器人地址: {}", bot_address);
+        msg!("交换路由器: {}", swap_router);
+        msg!("包装原生代币: {}", wrapped_native);
+        
+        // 验证参数
+        require!(bot_address != Pubkey::default(), ErrorCode::InvalidBotAddress);
+        require!(swap_router != Pubkey::default(), ErrorCode::InvalidSwapRouter);
+        require!(wrapped_native != Pubkey::default(), ErrorCode::InvalidWrappedNative);
+        require!(vault_admin != Pubkey::default(), ErrorCode::InvalidAdminAddress);
+
+        msg!("参数验证通过，设置金库数据...");
+
+        let vault = &mut ctx.accounts.vault;
+        
+        // 设置金库数据
+        vault.investor = ctx.accounts.user.key();
+        vault.admin = vault_admin;
+        vault.operator = operator;
+        vault.bot = bot_address;
+        vault.swap_router = swap_router;
+        vault.wrapped_native = wrapped_native;
+        vault.is_initialized = true;
+        vault.strategy_enabled = true;
+
+        msg!("余额管理器创建完成!");
+        msg!("金库地址: {}", ctx.accounts.vault.key());
+
+        emit!(BalanceManagerCreatedEvent {
+            user: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 初始化全局配置，调用者成为平台管理员
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        max_fee_rate: u64,     // 最大费率 (百万分之一)
+        default_fee_rate: u64, // 每笔交易费率 (百万分之一)
+        treasury: Pubkey,      // 平台金库地址
+    ) -> Result<()> {
+        msg!("开始初始化全局配置...");
+        msg!("平台管理员地址: {}", ctx.accounts.authority.key());
+        msg!("最大费率: {} (百万分之一)", max_fee_rate);
+        msg!("默认费率: {} (百万分之一)", default_fee_rate);
+        msg!("平台金库: {}", treasury);
+
+        validate_fee_config(max_fee_rate, default_fee_rate, treasury)?;
+
+        let config = &mut ctx.accounts.config;
+        config.authority = ctx.accounts.authority.key();
+        config.paused = false;
+        config.deposits_paused = false;
+        config.bump = ctx.bumps.config;
+        config.max_fee_rate = max_fee_rate;
+        config.default_fee_rate = default_fee_rate;
+        config.treasury = treasury;
+        config.pending_fee_config = None;
+
+        msg!("全局配置初始化完成!");
+        msg!("配置地址: {}", ctx.accounts.config.key());
+
+        Ok(())
+    }
+
+    /// 设置协议费率配置 (仅平台管理员)
+    /// 降低费率立即生效；提高最大费率或默认费率时排队，FEE_CONFIG_INCREASE_DELAY_SECONDS 后才能执行
+    pub fn set_fee_config(
+        ctx: Context<SetFeeConfig>,
+        max_fee_rate: u64,
+        default_fee_rate: u64,
+    ) -> Result<()> {
+        msg!("开始设置费率配置...");
+        msg!("用户地址: {}", ctx.accounts.authority.key());
+        msg!("最大费率: {} (百万分之一)", max_fee_rate);
+        msg!("默认费率: {} (百万分之一)", default_fee_rate);
+
+        let config = &mut ctx.accounts.config;
+
+        require!(ctx.accounts.authority.key() == config.authority, ErrorCode::Unauthorized);
+        validate_fee_config(max_fee_rate, default_fee_rate, config.treasury)?;
+
+        let now = Clock::get()?.unix_timestamp;
+
+        // 提高费率会立即影响所有金库，需要排队等待，给投资者留出退出时间
+        if max_fee_rate > config.max_fee_rate || default_fee_rate > config.default_fee_rate {
+            let eta = now
+                .checked_add(FEE_CONFIG_INCREASE_DELAY_SECONDS)
+                .ok_or(ErrorCode::MathOverflow)?;
+            config.pending_fee_config = Some(PendingFeeConfig {
+                max_fee_rate,
+                default_fee_rate,
+                eta,
+            });
+
+            msg!("费率提高已排队，可执行时间戳: {}", eta);
+
+            emit!(FeeConfigQueuedEvent {
+                max_fee_rate,
+                default_fee_rate,
+                proposed_by: ctx.accounts.authority.key(),
+                eta,
+                timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+            });
+
+            return Ok(());
+        }
+
+        config.max_fee_rate = max_fee_rate;
+        config.default_fee_rate = default_fee_rate;
+
+        msg!("费率配置更新完成!");
+
+        emit!(FeeConfigUpdatedEvent {
+            max_fee_rate,
+            default_fee_rate,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 执行到期的费率提高 (任何人都可以调用)
+    pub fn execute_fee_config_change(ctx: Context<ExecuteFeeConfigChange>) -> Result<()> {
+        msg!("开始执行费率配置变更...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+
+        let config = &mut ctx.accounts.config;
+        let pending = config.pending_fee_config.ok_or(ErrorCode::NoPendingFeeConfig)?;
+        let now = Clock::get()?.unix_timestamp;
+
+        msg!("可执行时间戳: {}，当前时间戳: {}", pending.eta, now);
+
+        require!(now >= pending.eta, ErrorCode::TimelockNotExpired);
+        validate_fee_config(pending.max_fee_rate, pending.default_fee_rate, config.treasury)?;
+
+        config.max_fee_rate = pending.max_fee_rate;
+        config.default_fee_rate = pending.default_fee_rate;
+        config.pending_fee_config = None;
+
+        msg!("费率配置更新完成!");
+
+        emit!(FeeConfigUpdatedEvent {
+            max_fee_rate: pending.max_fee_rate,
+            default_fee_rate: pending.default_fee_rate,
+            changed_by: ctx.accounts.user.key(),
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 取消排队中的费率提高 (仅平台管理员)
+    pub fn cancel_fee_config_change(ctx: Context<CancelFeeConfigChange>) -> Result<()> {
+        msg!("开始取消费率配置变更...");
+        msg!("用户地址: {}", ctx.accounts.authority.key());
+
+        let config = &mut ctx.accounts.config;
+
+        require!(ctx.accounts.authority.key() == config.authority, ErrorCode::Unauthorized);
+        let pending = config.pending_fee_config.take().ok_or(ErrorCode::NoPendingFeeConfig)?;
+
+        msg!("费率配置变更已取消!");
+
+        emit!(FeeConfigChangeCancelledEvent {
+            max_fee_rate: pending.max_fee_rate,
+            default_fee_rate: pending.default_fee_rate,
+            cancelled_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置单个金库的交易费率 (仅平台管理员)，None 表示恢复使用全局默认费率
+    /// 金库启用时间锁后只能立即降低费率，提高费率需通过 queue_config_change (FeeRate) 排队
+    pub fn set_vault_fee_rate(
+        ctx: Context<SetVaultFeeRate>,
+        fee_rate: Option<u64>, // 百万分之一
+    ) -> Result<()> {
+        msg!("开始设置金库交易费率...");
+        msg!("用户地址: {}", ctx.accounts.authority.key());
+        msg!("金库地址: {}", ctx.accounts.vault.key());
+        msg!("费率: {:?} (百万分之一)", fee_rate);
+
+        let config = &ctx.accounts.config;
+
+        require!(ctx.accounts.authority.key() == config.authority, ErrorCode::Unauthorized);
+        check_vault_fee_rate_change(&ctx.accounts.vault, config, fee_rate)?;
+
+        let vault = &mut ctx.accounts.vault;
+        let old_fee_rate = vault.fee_rate_override;
+        vault.fee_rate_override = fee_rate;
+
+        msg!("金库交易费率从 {:?} 更新为 {:?}", old_fee_rate, fee_rate);
+
+        emit!(VaultFeeRateUpdatedEvent {
+            vault: vault.key(),
+            old_fee_rate,
+            new_fee_rate: fee_rate,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 更换平台金库 (仅平台管理员)，之后的交易费用只能支付到新金库的关联代币账户
+    pub fn set_treasury(
+        ctx: Context<SetTreasury>,
+        new_treasury: Pubkey,
+    ) -> Result<()> {
+        msg!("开始更换平台金库...");
+        msg!("用户地址: {}", ctx.accounts.authority.key());
+        msg!("新平台金库: {}", new_treasury);
+
+        let config = &mut ctx.accounts.config;
+
+        require!(ctx.accounts.authority.key() == config.authority, ErrorCode::Unauthorized);
+        require!(new_treasury != Pubkey::default(), ErrorCode::InvalidTreasury);
+
+        let old_treasury = config.treasury;
+        config.treasury = new_treasury;
+
+        msg!("平台金库从 {} 更新为 {}", old_treasury, new_treasury);
+
+        emit!(TreasuryUpdatedEvent {
+            old_treasury,
+            new_treasury,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 暂停或恢复协议 (紧急熔断)
+    pub fn set_paused(
+        ctx: Context<SetPaused>,
+        paused: bool,
+        deposits_paused: bool,
+    ) -> Result<()> {
+        msg!("开始设置协议暂停状态...");
+        msg!("用户地址: {}", ctx.accounts.authority.key());
+        msg!("暂停交易: {}", paused);
+        msg!("暂停存款: {}", deposits_paused);
+
+        let config = &mut ctx.accounts.config;
+
+        require!(ctx.accounts.authority.key() == config.authority, ErrorCode::Unauthorized);
+
+        config.paused = paused;
+        config.deposits_paused = deposits_paused;
+
+        msg!("协议暂停状态更新完成!");
+
+        emit!(ProtocolPauseChangedEvent {
+            paused,
+            deposits_paused,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 初始化预言机注册表 (仅平台管理员)
+    pub fn initialize_oracle_registry(
+        ctx: Context<InitializeOracleRegistry>,
+        max_staleness_seconds: u64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        msg!("开始初始化预言机注册表...");
+        msg!("价格最大过期时间 (秒): {}", max_staleness_seconds);
+        msg!("最大置信区间 (基点): {}", max_confidence_bps);
+
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.config.authority,
+            ErrorCode::Unauthorized
+        );
+        require!(
+            max_confidence_bps as u64 <= oracle::BPS_DENOMINATOR,
+            ErrorCode::InvalidSlippageBps
+        );
+
+        let registry = &mut ctx.accounts.oracle_registry;
+        registry.max_staleness_seconds = max_staleness_seconds;
+        registry.max_confidence_bps = max_confidence_bps;
+        registry.bump = ctx.bumps.oracle_registry;
+        registry.oracle_authority = ctx.accounts.authority.key();
+        registry.max_price_age = DEFAULT_MAX_PRICE_AGE;
+        registry.twap_window_seconds = DEFAULT_TWAP_WINDOW_SECONDS;
+        registry.max_deviation_bps = DEFAULT_MAX_DEVIATION_BPS;
+        registry.min_price_sources = 1;
+
+        msg!("预言机注册表初始化完成!");
+        msg!("注册表地址: {}", ctx.accounts.oracle_registry.key());
+
+        Ok(())
+    }
+
+    /// 设置预言机价格时效性与置信区间限制 (仅平台管理员)
+    pub fn set_oracle_registry_limits(
+        ctx: Context<SetOracleRegistryLimits>,
+        max_staleness_seconds: u64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        msg!("开始设置预言机限制...");
+        msg!("价格最大过期时间 (秒): {}", max_staleness_seconds);
+        msg!("最大置信区间 (基点): {}", max_confidence_bps);
+
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.config.authority,
+            ErrorCode::Unauthorized
+        );
+        require!(
+            max_confidence_bps as u64 <= oracle::BPS_DENOMINATOR,
+            ErrorCode::InvalidSlippageBps
+        );
+
+        let registry = &mut ctx.accounts.oracle_registry;
+        registry.max_staleness_seconds = max_staleness_seconds;
+        registry.max_confidence_bps = max_confidence_bps;
+
+        msg!("预言机限制更新完成!");
+
+        emit!(OracleRegistryLimitsUpdatedEvent {
+            max_staleness_seconds,
+            max_confidence_bps,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置价格聚合参数 (仅平台管理员)
+    pub fn set_price_aggregation(
+        ctx: Context<SetPriceAggregation>,
+        min_price_sources: u8,
+        max_deviation_bps: u16,
+        twap_window_seconds: u32,
+    ) -> Result<()> {
+        msg!("开始设置价格聚合参数...");
+        msg!("最少价格源数量: {}", min_price_sources);
+        msg!("最大偏离 (基点): {}", max_deviation_bps);
+        msg!("TWAP 窗口 (秒): {}", twap_window_seconds);
+
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.config.authority,
+            ErrorCode::Unauthorized
+        );
+        require!(
+            min_price_sources > 0
+                && max_deviation_bps as u64 <= oracle::BPS_DENOMINATOR
+                && twap_window_seconds > 0,
+            ErrorCode::InvalidAggregationConfig
+        );
+
+        let registry = &mut ctx.accounts.oracle_registry;
+        registry.min_price_sources = min_price_sources;
+        registry.max_deviation_bps = max_deviation_bps;
+        registry.twap_window_seconds = twap_window_seconds;
+
+        msg!("价格聚合参数更新完成!");
+
+        emit!(PriceAggregationUpdatedEvent {
+            min_price_sources,
+            max_deviation_bps,
+            twap_window_seconds,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置代币的价格源 (仅平台管理员)
+    /// 同一代币的每种价格源各占一项，已存在时更新
+    /// Pyth 传入价格源 ID，Switchboard 传入 feed 账户地址，ClmmTwap 传入池子地址
+    pub fn set_price_feed(
+        ctx: Context<SetPriceFeed>,
+        mint: Pubkey,
+        source: PriceSource,
+        feed_id: [u8; 32],
+    ) -> Result<()> {
+        msg!("开始设置价格源...");
+        msg!("代币地址: {}", mint);
+        msg!("价格源类型: {:?}", source);
+
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.config.authority,
+            ErrorCode::Unauthorized
+        );
+        require!(mint != Pubkey::default(), ErrorCode::InvalidBaseMint);
+
+        let registry = &mut ctx.accounts.oracle_registry;
+        match registry
+            .feeds
+            .iter_mut()
+            .find(|entry| entry.mint == mint && entry.source == source)
+        {
+            Some(entry) => {
+                msg!("更新现有价格源");
+                entry.feed_id = feed_id;
+            }
+            None => {
+                require!(registry.feeds.len() < MAX_PRICE_FEEDS, ErrorCode::TooManyPriceFeeds);
+                msg!("添加新价格源");
+                registry.feeds.push(PriceFeedEntry { mint, source, feed_id });
+            }
+        }
+
+        msg!("价格源设置完成，当前价格源数量: {}", registry.feeds.len());
+
+        emit!(PriceFeedUpdatedEvent {
+            mint,
+            source,
+            feed_id,
+            removed: false,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 移除代币的价格源 (仅平台管理员)
+    pub fn remove_price_feed(
+        ctx: Context<RemovePriceFeed>,
+        mint: Pubkey,
+        source: PriceSource,
+    ) -> Result<()> {
+        msg!("开始移除价格源...");
+        msg!("代币地址: {}", mint);
+        msg!("价格源类型: {:?}", source);
+
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.config.authority,
+            ErrorCode::Unauthorized
+        );
+
+        let registry = &mut ctx.accounts.oracle_registry;
+        let index = registry
+            .feeds
+            .iter()
+            .position(|entry| entry.mint == mint && entry.source == source)
+            .ok_or(ErrorCode::PriceFeedNotConfigured)?;
+        let removed = registry.feeds.remove(index);
+
+        msg!("价格源移除完成，当前价格源数量: {}", registry.feeds.len());
+
+        emit!(PriceFeedUpdatedEvent {
+            mint,
+            source,
+            feed_id: removed.feed_id,
+            removed: true,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置喂价角色和最大价格过期时间 (仅平台管理员，对应 PriceOracle 的 setMaxPriceAge)
+    pub fn set_price_oracle_config(
+        ctx: Context<SetPriceOracleConfig>,
+        oracle_authority: Pubkey,
+        max_price_age: u64,
+    ) -> Result<()> {
+        msg!("开始设置管理员喂价配置...");
+        msg!("喂价角色地址: {}", oracle_authority);
+        msg!("最大价格过期时间 (秒): {}", max_price_age);
+
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.config.authority,
+            ErrorCode::Unauthorized
+        );
+        require!(oracle_authority != Pubkey::default(), ErrorCode::InvalidAdminAddress);
+
+        let registry = &mut ctx.accounts.oracle_registry;
+        registry.oracle_authority = oracle_authority;
+        registry.max_price_age = max_price_age;
+
+        msg!("管理员喂价配置更新完成!");
+
+        emit!(PriceOracleConfigUpdatedEvent {
+            oracle_authority,
+            max_price_age,
+            changed_by: ctx.accounts.authority.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 更新交易对价格，同时更新反向价格 (对应 PriceOracle 的 updatePrice)
+    pub fn update_price(
+        ctx: Context<UpdatePrice>,
+        token_a: Pubkey,
+        token_b: Pubkey,
+        price: u128, // 1 个 token_a 等于多少 token_b (乘以 10^18)
+    ) -> Result<()> {
+        msg!("开始更新价格...");
+        msg!("喂价地址: {}", ctx.accounts.oracle.key());
+
+        require!(
+            ctx.accounts.oracle.key() == ctx.accounts.oracle_registry.oracle_authority,
+            ErrorCode::Unauthorized
+        );
+        require!(
+            token_a != Pubkey::default() && token_b != Pubkey::default() && token_a != token_b,
+            ErrorCode::InvalidBaseMint
+        );
+        let reverse_price = reverse_pair_price(price)?;
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        ctx.accounts.pair_price.bump = ctx.bumps.pair_price;
+        write_pair_price(&mut ctx.accounts.pair_price, token_a, token_b, price, timestamp);
+
+        ctx.accounts.reverse_pair_price.bump = ctx.bumps.reverse_pair_price;
+        write_pair_price(&mut ctx.accounts.reverse_pair_price, token_b, token_a, reverse_price, timestamp);
+
+        msg!("价格更新完成!");
+        Ok(())
+    }
+
+    /// 批量更新交易对价格 (对应 PriceOracle 的 updatePricesBatch)
+    /// remaining_accounts 按顺序为每个交易对提供 2 个已存在的价格账户:
+    /// [price, token_a, token_b] 与 [price, token_b, token_a]
+    pub fn update_prices_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdatePricesBatch<'info>>,
+        token_as: Vec<Pubkey>,
+        token_bs: Vec<Pubkey>,
+        prices: Vec<u128>,
+    ) -> Result<()> {
+        msg!("开始批量更新价格...");
+        msg!("喂价地址: {}", ctx.accounts.oracle.key());
+        msg!("交易对数量: {}", token_as.len());
+
+        require!(
+            ctx.accounts.oracle.key() == ctx.accounts.oracle_registry.oracle_authority,
+            ErrorCode::Unauthorized
+        );
+        let length = token_as.len();
+        require!(length > 0, ErrorCode::InvalidBatchLength);
+        require!(
+            length == token_bs.len() && length == prices.len(),
+            ErrorCode::InvalidBatchLength
+        );
+        require!(ctx.remaining_accounts.len() == length * 2, ErrorCode::InvalidBatchLength);
+
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        for index in 0..length {
+            let (token_a, token_b, price) = (token_as[index], token_bs[index], prices[index]);
+            let reverse_price = reverse_pair_price(price)?;
+
+            for (info, from, to, value) in [
+                (&ctx.remaining_accounts[index * 2], token_a, token_b, price),
+                (&ctx.remaining_accounts[index * 2 + 1], token_b, token_a, reverse_price),
+            ] {
+                let (expected, _bump) = Pubkey::find_program_address(
+                    &[b"price", from.as_ref(), to.as_ref()],
+                    &crate::ID,
+                );
+                require!(info.key() == expected, ErrorCode::InvalidPriceAccount);
+
+                let mut pair = Account::<TokenPairPrice>::try_from(info)?;
+                write_pair_price(&mut pair, from, to, value, timestamp);
+                pair.exit(&crate::ID)?;
+            }
+        }
+
+        msg!("批量价格更新完成!");
+        Ok(())
+    }
+
+    /// 获取交易对价格 (对应 PriceOracle 的 getPrice)
+    pub fn get_price(
+        ctx: Context<GetPrice>,
+        token_a: Pubkey,
+        token_b: Pubkey,
+    ) -> Result<u128> {
+        msg!("查询价格: {} -> {}", token_a, token_b);
+
+        let price = get_fresh_pair_price(
+            &ctx.accounts.pair_price,
+            ctx.accounts.oracle_registry.max_price_age,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        msg!("查询到的价格: {}", price);
+        Ok(price)
+    }
+
+    /// 计算 token_a 数量以 token_b 计的价值 (对应 PriceOracle 的 getTokenValueInAsset)
+    /// 输入与输出均为代币最小单位
+    pub fn get_token_value_in_asset(
+        ctx: Context<GetTokenValueInAsset>,
+        amount_a: u64,
+    ) -> Result<u64> {
+        msg!("计算代币价值...");
+        msg!("代币 A: {}", ctx.accounts.mint_a.key());
+        msg!("代币 B: {}", ctx.accounts.mint_b.key());
+        msg!("数量: {}", amount_a);
+
+        let price = get_fresh_pair_price(
+            &ctx.accounts.pair_price,
+            ctx.accounts.oracle_registry.max_price_age,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let value = oracle::precision_price_value(
+            amount_a,
+            price,
+            ctx.accounts.mint_a.decimals,
+            ctx.accounts.mint_b.decimals,
+        )
+        .ok_or(ErrorCode::MathOverflow)?;
+
+        msg!("价值: {}", value);
+        Ok(value)
+    }
+
+    /// 设置机器人地址
+    pub fn set_bot(
+        ctx: Context<SetBot>,
+        new_bot_address: Pubkey,
+    ) -> Result<()> {
+        msg!("开始设置机器人地址...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("新机器人地址: {}", new_bot_address);
+        
+        require!(new_bot_address != Pubkey::default(), ErrorCode::InvalidBotAddress);
+        
+        let vault = &mut ctx.accounts.vault;
+        let old_bot_address = vault.bot;
+        
+        msg!("当前机器人地址: {}", old_bot_address);
+        
+        require!(new_bot_address != old_bot_address, ErrorCode::SameBotAddress);
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Admin, VaultRole::Operator])?;
+        // 启用时间锁后必须通过 queue_config_change 更换机器人
+        require!(vault.timelock_seconds == 0, ErrorCode::TimelockEnabled);
+
+        msg!("验证通过，更新机器人地址...");
+        
+        vault.bot = new_bot_address;
+        
+        msg!("机器人地址更新完成!");
+
+        Ok(())
+    }
+
+    /// 添加附加机器人或更新其权限与限制 (仅投资者)
+    pub fn add_bot(
+        ctx: Context<AddBot>,
+        bot: Pubkey,
+        permissions: u8,
+        max_trades_per_window: u32,
+        trade_window_slots: u64,
+        max_notional_per_trade: u64,
+        max_daily_notional: u64,
+    ) -> Result<()> {
+        msg!("开始添加机器人...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("机器人地址: {}", bot);
+        msg!("权限位掩码: {:#06b}", permissions);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(bot != Pubkey::default(), ErrorCode::InvalidBotAddress);
+        require!(bot != vault.bot, ErrorCode::SameBotAddress);
+        require!(
+            permissions != 0 && permissions & !BOT_PERMISSION_ALL == 0,
+            ErrorCode::InvalidBotPermissions
+        );
+        require!(
+            max_trades_per_window == 0 || trade_window_slots > 0,
+            ErrorCode::InvalidTradeLimits
+        );
+
+        match vault.bots.iter_mut().find(|entry| entry.key == bot) {
+            Some(entry) => {
+                msg!("机器人已存在，更新权限与限制");
+                entry.permissions = permissions;
+                entry.limits.max_trades_per_window = max_trades_per_window;
+                entry.limits.trade_window_slots = trade_window_slots;
+                entry.limits.max_notional_per_trade = max_notional_per_trade;
+                entry.limits.max_daily_notional = max_daily_notional;
+            }
+            None => {
+                require!(vault.bots.len() < MAX_VAULT_BOTS, ErrorCode::TooManyBots);
+                vault.bots.push(BotEntry {
+                    key: bot,
+                    permissions,
+                    limits: TradeLimits {
+                        max_trades_per_window,
+                        trade_window_slots,
+                        max_notional_per_trade,
+                        max_daily_notional,
+                        ..TradeLimits::default()
+                    },
+                });
+            }
+        }
+
+        msg!("机器人添加完成，当前附加机器人数量: {}", vault.bots.len());
+
+        emit!(BotAddedEvent {
+            user: ctx.accounts.user.key(),
+            bot,
+            permissions,
+            max_trades_per_window,
+            trade_window_slots,
+            max_notional_per_trade,
+            max_daily_notional,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 移除附加机器人 (仅投资者)
+    pub fn remove_bot(
+        ctx: Context<RemoveBot>,
+        bot: Pubkey,
+    ) -> Result<()> {
+        msg!("开始移除机器人...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("机器人地址: {}", bot);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor])?;
+
+        let index = vault
+            .bots
+            .iter()
+            .position(|entry| entry.key == bot)
+            .ok_or(ErrorCode::BotNotFound)?;
+        vault.bots.remove(index);
+
+        msg!("机器人移除完成，当前附加机器人数量: {}", vault.bots.len());
+
+        emit!(BotRemovedEvent {
+            user: ctx.accounts.user.key(),
+            bot,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 授予临时会话密钥 (投资者或管理员)，会话在指定 slot 或时间戳之前有效
+    pub fn create_session(
+        ctx: Context<CreateSession>,
+        session_key: Pubkey,
+        expires_slot: u64,
+        expires_at: i64,
+        max_trades: u32,
+    ) -> Result<()> {
+        msg!("开始创建会话密钥...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("会话密钥: {}", session_key);
+        msg!("过期 slot: {}", expires_slot);
+        msg!("过期时间戳: {}", expires_at);
+        msg!("最大交易次数: {}", max_trades);
+
+        let vault = &ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor, VaultRole::Admin])?;
+        // 启用时间锁后管理员授予会话必须通过 queue_config_change，投资者不受限制
+        if !vault.has_role(VaultRole::Investor, &ctx.accounts.user.key()) {
+            require!(vault.timelock_seconds == 0, ErrorCode::TimelockEnabled);
+        }
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(session_key != Pubkey::default(), ErrorCode::InvalidBotAddress);
+
+        let clock = Clock::get()?;
+        validate_session_expiry(
+            &SessionGrant {
+                expires_slot,
+                expires_at,
+                max_trades,
+            },
+            &clock,
+        )?;
+
+        let session = &mut ctx.accounts.session;
+        session.vault = vault.key();
+        session.session_key = session_key;
+        session.granted_by = ctx.accounts.user.key();
+        session.expires_slot = expires_slot;
+        session.expires_at = expires_at;
+        session.max_trades = max_trades;
+        session.trades_used = 0;
+        session.bump = ctx.bumps.session;
+
+        msg!("会话密钥创建完成!");
+        msg!("会话地址: {}", ctx.accounts.session.key());
+
+        emit!(SessionCreatedEvent {
+            vault: vault.key(),
+            session_key,
+            granted_by: ctx.accounts.user.key(),
+            expires_slot,
+            expires_at,
+            max_trades,
+            timestamp_microseconds: clock.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 撤销会话密钥 (投资者或管理员)，关闭会话账户并退还租金
+    pub fn revoke_session(ctx: Context<RevokeSession>) -> Result<()> {
+        msg!("开始撤销会话密钥...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("会话密钥: {}", ctx.accounts.session.session_key);
+
+        require_vault_role(
+            &ctx.accounts.vault,
+            ctx.accounts.user.key(),
+            &[VaultRole::Investor, VaultRole::Admin],
+        )?;
+
+        msg!("会话已执行交易次数: {}", ctx.accounts.session.trades_used);
+        msg!("会话密钥撤销完成!");
+
+        emit!(SessionRevokedEvent {
+            vault: ctx.accounts.vault.key(),
+            session_key: ctx.accounts.session.session_key,
+            revoked_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置敏感配置变更的延迟时间 (仅投资者)
+    pub fn set_timelock_delay(
+        ctx: Context<SetTimelockDelay>,
+        timelock_seconds: i64,
+    ) -> Result<()> {
+        msg!("开始设置时间锁延迟...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("延迟时间 (秒): {}", timelock_seconds);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor])?;
+        require!(
+            (0..=MAX_TIMELOCK_SECONDS).contains(&timelock_seconds),
+            ErrorCode::InvalidTimelockDelay
+        );
+
+        let old_timelock_seconds = vault.timelock_seconds;
+        vault.timelock_seconds = timelock_seconds;
+
+        msg!("时间锁延迟从 {} 秒更新为 {} 秒", old_timelock_seconds, timelock_seconds);
+
+        emit!(TimelockDelayUpdatedEvent {
+            vault: vault.key(),
+            old_timelock_seconds,
+            new_timelock_seconds: timelock_seconds,
+            changed_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 提议配置变更，延迟时间过后才能执行
+    pub fn queue_config_change(
+        ctx: Context<QueueConfigChange>,
+        kind: ConfigChangeKind,
+        new_address: Pubkey,
+        session_grant: Option<SessionGrant>, // 仅 Session 类型需要
+        new_fee_rate: Option<u64>,           // 仅 FeeRate 类型需要，None 表示恢复全局默认费率
+    ) -> Result<()> {
+        msg!("开始提议配置变更...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("变更类型: {:?}", kind);
+        msg!("新地址: {}", new_address);
+        msg!("新费率: {:?} (百万分之一)", new_fee_rate);
+
+        let vault = &ctx.accounts.vault;
+        let config = &ctx.accounts.config;
+
+        // 金库费率由平台管理员提议，其他变更由金库角色提议
+        let new_fee_rate = if kind == ConfigChangeKind::FeeRate {
+            require!(ctx.accounts.user.key() == config.authority, ErrorCode::Unauthorized);
+            if let Some(fee_rate) = new_fee_rate {
+                require!(fee_rate <= config.max_fee_rate, ErrorCode::InvalidFeeRate);
+            }
+            new_fee_rate
+        } else {
+            require_vault_role(vault, ctx.accounts.user.key(), config_change_roles(kind))?;
+            None
+        };
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+
+        let session_grant = validate_config_change(vault, kind, new_address, session_grant)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let eta = now
+            .checked_add(vault.timelock_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let change = &mut ctx.accounts.queued_change;
+        change.vault = vault.key();
+        change.kind = kind;
+        change.new_address = new_address;
+        change.session_grant = session_grant;
+        change.new_fee_rate = new_fee_rate;
+        change.proposed_by = ctx.accounts.user.key();
+        change.eta = eta;
+        change.bump = ctx.bumps.queued_change;
+
+        msg!("配置变更已排队，可执行时间戳: {}", eta);
+
+        emit!(ConfigChangeQueuedEvent {
+            vault: vault.key(),
+            kind,
+            new_address,
+            new_fee_rate,
+            proposed_by: ctx.accounts.user.key(),
+            eta,
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 执行到期的配置变更 (任何人都可以调用)
+    pub fn execute_config_change(ctx: Context<ExecuteConfigChange>) -> Result<()> {
+        msg!("开始执行配置变更...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+
+        let change = &ctx.accounts.queued_change;
+        let now = Clock::get()?.unix_timestamp;
+
+        msg!("变更类型: {:?}", change.kind);
+        msg!("可执行时间戳: {}，当前时间戳: {}", change.eta, now);
+
+        require!(now >= change.eta, ErrorCode::TimelockNotExpired);
+        // 只有 Session 类型需要 (且必须) 传入会话账户
+        require!(
+            ctx.accounts.session.is_some() == (change.kind == ConfigChangeKind::Session),
+            ErrorCode::InvalidSessionAccount
+        );
+
+        let vault = &mut ctx.accounts.vault;
+        match change.kind {
+            ConfigChangeKind::Bot => {
+                msg!("机器人地址从 {} 更新为 {}", vault.bot, change.new_address);
+                vault.bot = change.new_address;
+            }
+            ConfigChangeKind::Admin => {
+                msg!("提议新管理员 {}，等待接受", change.new_address);
+                vault.pending_admin = change.new_address;
+                emit!(AdminProposedEvent {
+                    current_admin: vault.admin,
+                    pending_admin: change.new_address,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            ConfigChangeKind::SwapRouter => {
+                msg!("交换路由器从 {} 更新为 {}", vault.swap_router, change.new_address);
+                vault.swap_router = change.new_address;
+            }
+            ConfigChangeKind::Session => {
+                let grant = change.session_grant.ok_or(ErrorCode::InvalidSessionExpiry)?;
+                validate_session_expiry(&grant, &Clock::get()?)?;
+
+                let session = ctx.accounts.session.as_mut().ok_or(ErrorCode::InvalidSessionAccount)?;
+                session.vault = vault.key();
+                session.session_key = change.new_address;
+                session.granted_by = change.proposed_by;
+                session.expires_slot = grant.expires_slot;
+                session.expires_at = grant.expires_at;
+                session.max_trades = grant.max_trades;
+                session.trades_used = 0;
+                session.bump = ctx.bumps.session.ok_or(ErrorCode::InvalidSessionAccount)?;
+
+                msg!("会话密钥 {} 授权完成", change.new_address);
+                emit!(SessionCreatedEvent {
+                    vault: vault.key(),
+                    session_key: change.new_address,
+                    granted_by: change.proposed_by,
+                    expires_slot: grant.expires_slot,
+                    expires_at: grant.expires_at,
+                    max_trades: grant.max_trades,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            ConfigChangeKind::FeeRate => {
+                // 执行时超过最大费率的部分在交易时按最大费率收取 (见 vault_fee_rate)
+                let old_fee_rate = vault.fee_rate_override;
+                vault.fee_rate_override = change.new_fee_rate;
+                msg!("金库交易费率从 {:?} 更新为 {:?}", old_fee_rate, change.new_fee_rate);
+                emit!(VaultFeeRateUpdatedEvent {
+                    vault: vault.key(),
+                    old_fee_rate,
+                    new_fee_rate: change.new_fee_rate,
+                    changed_by: change.proposed_by,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+        }
+
+        msg!("配置变更执行完成!");
+
+        emit!(ConfigChangeExecutedEvent {
+            vault: vault.key(),
+            kind: change.kind,
+            new_address: change.new_address,
+            executed_by: ctx.accounts.user.key(),
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 取消排队中的配置变更 (投资者、管理员或提议者)
+    pub fn cancel_config_change(ctx: Context<CancelConfigChange>) -> Result<()> {
+        msg!("开始取消配置变更...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+
+        let change = &ctx.accounts.queued_change;
+        let user = ctx.accounts.user.key();
+
+        msg!("变更类型: {:?}", change.kind);
+
+        require!(
+            user == change.proposed_by
+                || ctx.accounts.vault.has_role(VaultRole::Investor, &user)
+                || ctx.accounts.vault.has_role(VaultRole::Admin, &user),
+            ErrorCode::Unauthorized
+        );
+
+        msg!("配置变更已取消!");
+
+        emit!(ConfigChangeCancelledEvent {
+            vault: ctx.accounts.vault.key(),
+            kind: change.kind,
+            cancelled_by: user,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 创建多签管理员 (仅当前管理员)，多签 PDA 立即成为金库管理员
+    pub fn create_admin_multisig(
+        ctx: Context<CreateAdminMultisig>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        msg!("开始创建多签管理员...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("签名者数量: {}", signers.len());
+        msg!("审批阈值: {}", threshold);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Admin])?;
+        validate_multisig_signers(&signers, threshold)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.vault = vault.key();
+        multisig.signers = signers.clone();
+        multisig.threshold = threshold;
+        multisig.signers_version = 0;
+        multisig.proposal_count = 0;
+        multisig.bump = ctx.bumps.multisig;
+
+        // 启用时间锁后不立即移交管理员，需通过 queue_config_change (Admin) 提议多签，
+        // 再由多签执行 AcceptAdmin 提案接受
+        if vault.timelock_seconds == 0 {
+            let old_admin = vault.admin;
+            vault.admin = multisig.key();
+            vault.pending_admin = Pubkey::default();
+            msg!("管理员从 {} 更新为多签 {}", old_admin, vault.admin);
+        } else {
+            msg!("已启用时间锁，管理员需通过 queue_config_change 移交给多签 {}", multisig.key());
+        }
+
+        emit!(AdminMultisigCreatedEvent {
+            vault: vault.key(),
+            multisig: multisig.key(),
+            signers,
+            threshold,
+            created_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 创建多签提案 (仅多签签名者)，提议者自动审批
+    pub fn create_multisig_proposal(
+        ctx: Context<CreateMultisigProposal>,
+        action: MultisigAction,
+    ) -> Result<()> {
+        msg!("开始创建多签提案...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("提案操作: {:?}", action);
+
+        let multisig = &mut ctx.accounts.multisig;
+        let signer_index = multisig_signer_index(multisig, &ctx.accounts.user.key())?;
+
+        if let MultisigAction::SetSigners { signers, threshold } = &action {
+            validate_multisig_signers(signers, *threshold)?;
+        }
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.multisig = multisig.key();
+        proposal.index = multisig.proposal_count;
+        proposal.action = action;
+        proposal.approvals = 1 << signer_index;
+        proposal.signers_version = multisig.signers_version;
+        proposal.proposer = ctx.accounts.user.key();
+        proposal.bump = ctx.bumps.proposal;
+
+        multisig.proposal_count += 1;
+
+        msg!("多签提案创建完成，编号: {}", proposal.index);
+
+        emit!(MultisigProposalCreatedEvent {
+            multisig: multisig.key(),
+            index: proposal.index,
+            proposer: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 审批多签提案 (仅多签签名者)
+    pub fn approve_multisig_proposal(ctx: Context<ApproveMultisigProposal>) -> Result<()> {
+        msg!("开始审批多签提案...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+
+        let multisig = &ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+
+        msg!("提案编号: {}", proposal.index);
+
+        let approvals = record_multisig_approval(multisig, proposal, &ctx.accounts.user.key())?;
+
+        msg!("当前审批数量: {} / {}", approvals, multisig.threshold);
+
+        emit!(MultisigProposalApprovedEvent {
+            multisig: multisig.key(),
+            index: proposal.index,
+            approver: ctx.accounts.user.key(),
+            approvals,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 执行已达到阈值的多签提案 (任何人都可以调用)
+    pub fn execute_multisig_proposal(ctx: Context<ExecuteMultisigProposal>) -> Result<()> {
+        msg!("开始执行多签提案...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+
+        let multisig = &mut ctx.accounts.multisig;
+        let proposal = &ctx.accounts.proposal;
+        let vault = &mut ctx.accounts.vault;
+        let now = Clock::get()?.unix_timestamp;
+
+        msg!("提案编号: {}", proposal.index);
+        msg!("提案操作: {:?}", proposal.action);
+
+        // 多签只有成为管理员后才能执行管理员操作，AcceptAdmin 要求多签是待定管理员
+        if let MultisigAction::AcceptAdmin = proposal.action {
+            require!(vault.pending_admin == multisig.key(), ErrorCode::OnlyPendingAdmin);
+        } else {
+            require!(vault.admin == multisig.key(), ErrorCode::Unauthorized);
+        }
+        // 只有对应的操作需要 (且必须) 传入排队变更账户或会话账户
+        require!(
+            ctx.accounts.queued_change.is_some() == proposal.action.config_change_kind().is_some(),
+            ErrorCode::InvalidMultisigAccounts
+        );
+        require!(
+            ctx.accounts.session.is_some() == proposal.action.session_key().is_some(),
+            ErrorCode::InvalidMultisigAccounts
+        );
+        proposal.action.check_target_accounts(
+            ctx.accounts.cancelled_change.as_deref(),
+            ctx.accounts.revoked_session.as_deref(),
+        )?;
+        check_multisig_executable(multisig, proposal)?;
+
+        match &proposal.action {
+            MultisigAction::SetBot { new_bot } => {
+                require!(*new_bot != Pubkey::default(), ErrorCode::InvalidBotAddress);
+                require!(*new_bot != vault.bot, ErrorCode::SameBotAddress);
+                require!(vault.timelock_seconds == 0, ErrorCode::TimelockEnabled);
+                msg!("机器人地址从 {} 更新为 {}", vault.bot, new_bot);
+                vault.bot = *new_bot;
+            }
+            MultisigAction::ProposeAdmin { new_admin } => {
+                require!(*new_admin != Pubkey::default(), ErrorCode::InvalidAdminAddress);
+                require!(*new_admin != vault.admin, ErrorCode::SameAdminAddress);
+                require!(vault.timelock_seconds == 0, ErrorCode::TimelockEnabled);
+                vault.pending_admin = *new_admin;
+                msg!("提议新管理员 {}，等待接受", new_admin);
+                emit!(AdminProposedEvent {
+                    current_admin: vault.admin,
+                    pending_admin: *new_admin,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::SetOperator { new_operator } => {
+                let old_operator = vault.operator;
+                vault.operator = *new_operator;
+                msg!("运营方地址从 {} 更新为 {}", old_operator, new_operator);
+                emit!(OperatorUpdatedEvent {
+                    old_operator,
+                    new_operator: *new_operator,
+                    changed_by: multisig.key(),
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::SetStrategyEnabled { enabled } => {
+                vault.strategy_enabled = *enabled;
+                msg!("策略启用: {}", enabled);
+                emit!(StrategyStatusChangedEvent {
+                    enabled: *enabled,
+                    changed_by: multisig.key(),
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::SetSigners { signers, threshold } => {
+                validate_multisig_signers(signers, *threshold)?;
+                multisig.signers = signers.clone();
+                multisig.threshold = *threshold;
+                multisig.signers_version += 1;
+                msg!("签名者集合更新，数量: {}，阈值: {}", signers.len(), threshold);
+            }
+            MultisigAction::QueueConfigChange {
+                kind,
+                new_address,
+                session_grant,
+            } => {
+                require!(
+                    config_change_roles(*kind).contains(&VaultRole::Admin),
+                    ErrorCode::Unauthorized
+                );
+                let session_grant = validate_config_change(vault, *kind, *new_address, *session_grant)?;
+                let eta = now
+                    .checked_add(vault.timelock_seconds)
+                    .ok_or(ErrorCode::MathOverflow)?;
+
+                let change = ctx.accounts.queued_change.as_mut().ok_or(ErrorCode::InvalidMultisigAccounts)?;
+                change.vault = vault.key();
+                change.kind = *kind;
+                change.new_address = *new_address;
+                change.session_grant = session_grant;
+                change.new_fee_rate = None;
+                change.proposed_by = multisig.key();
+                change.eta = eta;
+                change.bump = ctx.bumps.queued_change.ok_or(ErrorCode::InvalidMultisigAccounts)?;
+
+                msg!("配置变更已排队，类型: {:?}，可执行时间戳: {}", kind, eta);
+                emit!(ConfigChangeQueuedEvent {
+                    vault: vault.key(),
+                    kind: *kind,
+                    new_address: *new_address,
+                    new_fee_rate: None,
+                    proposed_by: multisig.key(),
+                    eta,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::AcceptAdmin => {
+                let old_admin = vault.admin;
+                vault.admin = multisig.key();
+                vault.pending_admin = Pubkey::default();
+                msg!("管理员地址从 {} 更新为多签 {}", old_admin, vault.admin);
+                emit!(AdminTransferredEvent {
+                    old_admin,
+                    new_admin: vault.admin,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::CancelAdminTransfer => {
+                require!(vault.pending_admin != Pubkey::default(), ErrorCode::NoPendingAdmin);
+                let cancelled_admin = vault.pending_admin;
+                vault.pending_admin = Pubkey::default();
+                msg!("管理员转移已取消!");
+                emit!(AdminTransferCancelledEvent {
+                    admin: vault.admin,
+                    cancelled_admin,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::CreateSession { session_key, grant } => {
+                require!(*session_key != Pubkey::default(), ErrorCode::InvalidBotAddress);
+                // 启用时间锁后必须通过 QueueConfigChange (Session) 授予会话
+                require!(vault.timelock_seconds == 0, ErrorCode::TimelockEnabled);
+                validate_session_expiry(grant, &Clock::get()?)?;
+
+                let session = ctx.accounts.session.as_mut().ok_or(ErrorCode::InvalidMultisigAccounts)?;
+                session.vault = vault.key();
+                session.session_key = *session_key;
+                session.granted_by = multisig.key();
+                session.expires_slot = grant.expires_slot;
+                session.expires_at = grant.expires_at;
+                session.max_trades = grant.max_trades;
+                session.trades_used = 0;
+                session.bump = ctx.bumps.session.ok_or(ErrorCode::InvalidMultisigAccounts)?;
+
+                msg!("会话密钥 {} 授权完成", session_key);
+                emit!(SessionCreatedEvent {
+                    vault: vault.key(),
+                    session_key: *session_key,
+                    granted_by: multisig.key(),
+                    expires_slot: grant.expires_slot,
+                    expires_at: grant.expires_at,
+                    max_trades: grant.max_trades,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::SetTradeLimits {
+                max_trades_per_window,
+                trade_window_slots,
+                max_notional_per_trade,
+                max_daily_notional,
+            } => {
+                require!(
+                    *max_trades_per_window == 0 || *trade_window_slots > 0,
+                    ErrorCode::InvalidTradeLimits
+                );
+                let limits = &mut vault.trade_limits;
+                limits.max_trades_per_window = *max_trades_per_window;
+                limits.trade_window_slots = *trade_window_slots;
+                limits.max_notional_per_trade = *max_notional_per_trade;
+                limits.max_daily_notional = *max_daily_notional;
+                msg!("交易限制更新完成!");
+                emit!(TradeLimitsUpdatedEvent {
+                    user: multisig.key(),
+                    max_trades_per_window: *max_trades_per_window,
+                    trade_window_slots: *trade_window_slots,
+                    max_notional_per_trade: *max_notional_per_trade,
+                    max_daily_notional: *max_daily_notional,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::SetOracleCheck {
+                enabled,
+                max_slippage_bps,
+            } => {
+                require!(
+                    *max_slippage_bps as u64 <= oracle::BPS_DENOMINATOR,
+                    ErrorCode::InvalidSlippageBps
+                );
+                vault.oracle_check_enabled = *enabled;
+                vault.max_oracle_slippage_bps = *max_slippage_bps;
+                msg!("预言机检查设置完成，启用: {}，最大滑点 (基点): {}", enabled, max_slippage_bps);
+                emit!(OracleCheckUpdatedEvent {
+                    user: multisig.key(),
+                    enabled: *enabled,
+                    max_slippage_bps: *max_slippage_bps,
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::CancelConfigChange { kind } => {
+                let change = ctx.accounts.cancelled_change.as_ref().ok_or(ErrorCode::InvalidMultisigAccounts)?;
+                let change_proposer = ctx.accounts.change_proposer.as_ref().ok_or(ErrorCode::InvalidMultisigAccounts)?;
+                require!(
+                    change_proposer.key() == change.proposed_by,
+                    ErrorCode::InvalidMultisigAccounts
+                );
+                change.close(change_proposer.to_account_info())?;
+                msg!("配置变更已取消，类型: {:?}", kind);
+                emit!(ConfigChangeCancelledEvent {
+                    vault: vault.key(),
+                    kind: *kind,
+                    cancelled_by: multisig.key(),
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+            MultisigAction::RevokeSession { session_key } => {
+                let session = ctx.accounts.revoked_session.as_ref().ok_or(ErrorCode::InvalidMultisigAccounts)?;
+                msg!("会话已执行交易次数: {}", session.trades_used);
+                session.close(ctx.accounts.user.to_account_info())?;
+                msg!("会话密钥 {} 撤销完成!", session_key);
+                emit!(SessionRevokedEvent {
+                    vault: vault.key(),
+                    session_key: *session_key,
+                    revoked_by: multisig.key(),
+                    timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+                });
+            }
+        }
+
+        msg!("多签提案执行完成!");
+
+        emit!(MultisigProposalExecutedEvent {
+            multisig: multisig.key(),
+            index: proposal.index,
+            executed_by: ctx.accounts.user.key(),
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 取消多签提案 (仅提议者)，关闭提案账户并退还租金
+    pub fn cancel_multisig_proposal(ctx: Context<CancelMultisigProposal>) -> Result<()> {
+        msg!("开始取消多签提案...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("提案编号: {}", ctx.accounts.proposal.index);
+
+        require!(
+            ctx.accounts.user.key() == ctx.accounts.proposal.proposer,
+            ErrorCode::Unauthorized
+        );
+
+        msg!("多签提案已取消!");
+
+        emit!(MultisigProposalCancelledEvent {
+            multisig: ctx.accounts.multisig.key(),
+            index: ctx.accounts.proposal.index,
+            cancelled_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置签名交易意图模式 (仅投资者)，启用后机器人只能转发投资者或策略签名者签名的交易
+    pub fn set_intent_mode(
+        ctx: Context<SetIntentMode>,
+        require_signed_intents: bool,
+        intent_signer: Pubkey,
+    ) -> Result<()> {
+        msg!("开始设置签名交易意图模式...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("启用: {}", require_signed_intents);
+        msg!("策略签名者: {}", intent_signer);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+
+        vault.require_signed_intents = require_signed_intents;
+        vault.intent_signer = intent_signer;
+
+        msg!("签名交易意图模式更新完成!");
+
+        emit!(IntentModeUpdatedEvent {
+            vault: vault.key(),
+            require_signed_intents,
+            intent_signer,
+            changed_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置平台运营方 (仅金库管理员)，Pubkey::default() 表示撤销
+    pub fn set_operator(
+        ctx: Context<SetOperator>,
+        new_operator: Pubkey,
+    ) -> Result<()> {
+        msg!("开始设置运营方...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("新运营方地址: {}", new_operator);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Admin])?;
+
+        let old_operator = vault.operator;
+        vault.operator = new_operator;
+
+        msg!("运营方地址从 {} 更新为 {}", old_operator, new_operator);
+
+        emit!(OperatorUpdatedEvent {
+            old_operator,
+            new_operator,
+            changed_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 提议新管理员 (两步转移的第一步)
+    pub fn propose_admin(
+        ctx: Context<ProposeAdmin>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        msg!("开始提议新管理员...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("新管理员地址: {}", new_admin);
+        
+        require!(new_admin != Pubkey::default(), ErrorCode::InvalidAdminAddress);
+        
+        let vault = &mut ctx.accounts.vault;
+        
+        msg!("当前管理员地址: {}", vault.admin);
+        
+        require!(new_admin != vault.admin, ErrorCode::SameAdminAddress);
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Admin])?;
+        // 启用时间锁后必须通过 queue_config_change 提议新管理员
+        require!(vault.timelock_seconds == 0, ErrorCode::TimelockEnabled);
+
+        vault.pending_admin = new_admin;
+        
+        msg!("新管理员提议完成，等待接受!");
+
+        emit!(AdminProposedEvent {
+            current_admin: vault.admin,
+            pending_admin: new_admin,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+        
+        Ok(())
+    }
+
+    /// 接受管理员身份 (两步转移的第二步，由待定管理员调用)
+    pub fn accept_admin(
+        ctx: Context<AcceptAdmin>,
+    ) -> Result<()> {
+        msg!("开始接受管理员身份...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        
+        let vault = &mut ctx.accounts.vault;
+        
+        require!(vault.pending_admin != Pubkey::default(), ErrorCode::NoPendingAdmin);
+        require!(ctx.accounts.user.key() == vault.pending_admin, ErrorCode::OnlyPendingAdmin);
+
+        let old_admin = vault.admin;
+        vault.admin = vault.pending_admin;
+        vault.pending_admin = Pubkey::default();
+        
+        msg!("管理员地址从 {} 更新为 {}", old_admin, vault.admin);
+
+        emit!(AdminTransferredEvent {
+            old_admin,
+            new_admin: vault.admin,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+        
+        Ok(())
+    }
+
+    /// 取消待定的管理员转移 (当前管理员调用)
+    pub fn cancel_admin_transfer(
+        ctx: Context<CancelAdminTransfer>,
+    ) -> Result<()> {
+        msg!("开始取消管理员转移...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        
+        let vault = &mut ctx.accounts.vault;
+        
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Admin])?;
+        require!(vault.pending_admin != Pubkey::default(), ErrorCode::NoPendingAdmin);
+
+        let cancelled_admin = vault.pending_admin;
+        vault.pending_admin = Pubkey::default();
+        
+        msg!("管理员转移已取消!");
+
+        emit!(AdminTransferCancelledEvent {
+            admin: vault.admin,
+            cancelled_admin,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+        
+        Ok(())
+    }
+
+    /// 启用或禁用策略执行 (对应 UniswapVault 的 setStrategyEnabled)
+    pub fn set_strategy_enabled(
+        ctx: Context<SetStrategyEnabled>,
+        enabled: bool,
+    ) -> Result<()> {
+        msg!("开始设置策略状态...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("策略启用: {}", enabled);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(
+            vault,
+            ctx.accounts.user.key(),
+            &[VaultRole::Investor, VaultRole::Admin, VaultRole::Operator],
+        )?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+
+        vault.strategy_enabled = enabled;
+
+        msg!("策略状态更新完成!");
+
+        emit!(StrategyStatusChangedEvent {
+            enabled,
+            changed_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置机器人交易限制 (0 表示不限制)
+    pub fn set_trade_limits(
+        ctx: Context<SetTradeLimits>,
+        max_trades_per_window: u32,
+        trade_window_slots: u64,
+        max_notional_per_trade: u64,
+        max_daily_notional: u64,
+    ) -> Result<()> {
+        msg!("开始设置交易限制...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("窗口最大交易次数: {}", max_trades_per_window);
+        msg!("窗口长度 (slot): {}", trade_window_slots);
+        msg!("单笔最大名义金额: {}", max_notional_per_trade);
+        msg!("每日最大名义金额: {}", max_daily_notional);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor, VaultRole::Admin])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(
+            max_trades_per_window == 0 || trade_window_slots > 0,
+            ErrorCode::InvalidTradeLimits
+        );
+
+        let limits = &mut vault.trade_limits;
+        limits.max_trades_per_window = max_trades_per_window;
+        limits.trade_window_slots = trade_window_slots;
+        limits.max_notional_per_trade = max_notional_per_trade;
+        limits.max_daily_notional = max_daily_notional;
+
+        msg!("交易限制更新完成!");
+
+        emit!(TradeLimitsUpdatedEvent {
+            user: ctx.accounts.user.key(),
+            max_trades_per_window,
+            trade_window_slots,
+            max_notional_per_trade,
+            max_daily_notional,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置预言机最小输出检查
+    pub fn set_oracle_check(
+        ctx: Context<SetOracleCheck>,
+        enabled: bool,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        msg!("开始设置预言机检查...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("启用: {}", enabled);
+        msg!("最大滑点 (基点): {}", max_slippage_bps);
+
+        let vault = &mut ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor, VaultRole::Admin])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(
+            max_slippage_bps as u64 <= oracle::BPS_DENOMINATOR,
+            ErrorCode::InvalidSlippageBps
+        );
+
+        vault.oracle_check_enabled = enabled;
+        vault.max_oracle_slippage_bps = max_slippage_bps;
+
+        msg!("预言机检查设置完成!");
+
+        emit!(OracleCheckUpdatedEvent {
+            user: ctx.accounts.user.key(),
+            enabled,
+            max_slippage_bps,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 用户存款函数 (对应 Aptos 的 user_deposit)
+    pub fn user_deposit(
+        ctx: Context<UserDeposit>,
+        amount: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        
+        msg!("开始用户存款操作...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("代币地址: {}", ctx.accounts.mint.key());
+        msg!("存款金额: {}", amount);
+        
+        // 验证调用者是投资者
+        require!(vault.has_role(VaultRole::Investor, &ctx.accounts.user.key()), ErrorCode::OnlyInvestor);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(!ctx.accounts.config.deposits_paused, ErrorCode::DepositsPaused);
+
+        msg!("验证通过，开始更新余额...");
+        
+        // 更新余额
+        let current_balance = get_token_balance(vault, ctx.accounts.mint.key());
+        msg!("当前余额: {}", current_balance);
+        
+        set_token_balance(vault, ctx.accounts.mint.key(), current_balance + amount);
+        let new_balance = get_token_balance(vault, ctx.accounts.mint.key());
+        msg!("更新后余额: {}", new_balance);
+
+        emit!(UserDepositEvent {
+            user: ctx.accounts.user.key(),
+            asset_metadata: ctx.accounts.mint.key(), // 对应 Aptos 的 Object<Metadata>
+            amount,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        msg!("用户存款操作完成!");
+        Ok(())
+    }
+
+    /// 用户取款函数 (对应 Aptos 的 user_withdraw)
+    pub fn user_withdraw(
+        ctx: Context<UserWithdraw>,
+        amount: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        
+        msg!("开始用户取款操作...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("代币地址: {}", ctx.accounts.mint.key());
+        msg!("取款金额: {}", amount);
+        
+        // 验证调用者是投资者
+        require!(vault.has_role(VaultRole::Investor, &ctx.accounts.user.key()), ErrorCode::OnlyInvestor);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+
+        msg!("验证通过，检查余额...");
+        
+        // 检查余额
+        let current_balance = get_token_balance(vault, ctx.accounts.mint.key());
+        msg!("当前余额: {}", current_balance);
+        msg!("需要取款金额: {}", amount);
+        
+        require!(current_balance >= amount, ErrorCode::InsufficientBalance);
+
+        msg!("余额充足，开始更新余额...");
+        
+        // 更新余额
+        set_token_balance(vault, ctx.accounts.mint.key(), current_balance - amount);
+        let new_balance = get_token_balance(vault, ctx.accounts.mint.key());
+        msg!("更新后余额: {}", new_balance);
+
+        emit!(UserWithdrawEvent {
+            user: ctx.accounts.user.key(),
+            asset_metadata: ctx.accounts.mint.key(), // 对应 Aptos 的 Object<Metadata>
+            amount,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        msg!("用户取款操作完成!");
+        Ok(())
+    }
+
+    /// 获取代币余额
+    pub fn get_balance(
+        ctx: Context<GetBalance>,
+        token: Pubkey,
+    ) -> Result<u64> {
+        let vault = &ctx.accounts.vault;
+        msg!("查询代币余额...");
+        msg!("代币地址: {}", token);
+        
+        let balance = get_token_balance(vault, token);
+        msg!("查询到的余额: {}", balance);
+        
+        Ok(balance)
+    }
+
+    /// 查询投资组合构成 (对应 UniswapVault 的 getPortfolioComposition)
+    /// 只读指令，可通过 simulateTransaction 获取返回数据
+    /// remaining_accounts 需包含各代币的价格账户与代币 (Mint) 账户
+    pub fn get_portfolio_composition(
+        ctx: Context<GetPortfolioComposition>,
+        base_mint: Pubkey,
+    ) -> Result<PortfolioComposition> {
+        msg!("查询投资组合构成...");
+
+        value_portfolio(
+            &ctx.accounts.vault,
+            &ctx.accounts.oracle_registry,
+            base_mint,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// 查询总资产净值 (对应 UniswapVault 的 totalAssets)
+    /// remaining_accounts 需包含各代币的价格账户与代币 (Mint) 账户
+    pub fn total_assets(
+        ctx: Context<GetPortfolioComposition>,
+        base_mint: Pubkey,
+    ) -> Result<u64> {
+        msg!("查询总资产净值...");
+
+        let composition = value_portfolio(
+            &ctx.accounts.vault,
+            &ctx.accounts.oracle_registry,
+            base_mint,
+            ctx.remaining_accounts,
+        )?;
+
+        Ok(composition.total_value)
+    }
+
+    /// 发送交易信号并执行 DEX 交易 (对应 Aptos 的 send_trade_signal)
+    pub fn send_trade_signal(
+        ctx: Context<SendTradeSignal>,
+        token_in: Pubkey,
+        token_out: Pubkey,
+        amount_in: u64,
+        amount_out_minimum: u64,
+        signal_id: u64,        // 交易信号 ID，最近执行过的 ID 会被拒绝
+        valid_until_slot: u64, // 信号有效截止 slot
+    ) -> Result<u64> {
+        // 费率优先使用金库费率，否则使用全局默认费率，按百万分之一为基本单位 (1 = 0.0001%)
+        let fee_rate = vault_fee_rate(&ctx.accounts.vault, &ctx.accounts.config)?;
+
+        msg!("开始发送交易信号操作...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("输入代币: {}", token_in);
+        msg!("输出代币: {}", token_out);
+        msg!("输入金额: {}", amount_in);
+        msg!("最小输出金额: {}", amount_out_minimum);
+        msg!("费率: {} (百万分之一)", fee_rate);
+        msg!("交易信号 ID: {}", signal_id);
+        msg!("有效截止 slot: {}", valid_until_slot);
+        
+        // 验证调用者是拥有交换权限的机器人，或持有有效会话的会话密钥
+        let clock = Clock::get()?;
+        require!(!ctx.accounts.config.paused, ErrorCode::ProtocolPaused);
+        require!(ctx.accounts.vault.strategy_enabled, ErrorCode::StrategyDisabled);
+        let caller = ctx.accounts.user.key();
+        if ctx.accounts.vault.bot_permissions(&caller).is_some() {
+            require_bot_permission(&ctx.accounts.vault, caller, BOT_PERMISSION_SWAP)?;
+        } else {
+            let session = ctx.accounts.session.as_mut().ok_or(ErrorCode::OnlyBot)?;
+            consume_bot_session(session, clock.slot, clock.unix_timestamp)?;
+        }
+        require!(amount_in > 0, ErrorCode::InvalidAmount);
+        require!(ctx.accounts.vault.is_initialized, ErrorCode::VaultNotInitialized);
+        
+        // 信号过期与重放检查
+        require!(clock.slot <= valid_until_slot, ErrorCode::SignalExpired);
+        record_signal_id(&mut ctx.accounts.vault, signal_id)?;
+        
+        // 签名交易意图模式下，交易参数必须由投资者或策略签名者签名
+        if ctx.accounts.vault.require_signed_intents {
+            let instructions_sysvar = ctx
+                .accounts
+                .instructions_sysvar
+                .as_ref()
+                .ok_or(ErrorCode::MissingSignedIntent)?;
+            let vault_key = ctx.accounts.vault.key();
+            verify_signed_intent(
+                &mut ctx.accounts.vault,
+                vault_key,
+                instructions_sysvar,
+                token_in,
+                token_out,
+                amount_in,
+                amount_out_minimum,
+                clock.unix_timestamp,
+            )?;
+        }
+        
+        // 代币账户必须与信号中的代币一致，费用接收者按输出代币绑定
+        require!(ctx.accounts.input_token_account.mint == token_in, ErrorCode::InvalidSwapAccounts);
+        require!(ctx.accounts.output_token_account.mint == token_out, ErrorCode::InvalidSwapAccounts);
+        
+        // 检查输入代币余额
+        let current_balance = get_token_balance(&ctx.accounts.vault, token_in);
+        require!(current_balance >= amount_in, ErrorCode::InsufficientBalance);
+        
+        // 预言机最小输出检查
+        if ctx.accounts.vault.oracle_check_enabled {
+            let registry = ctx
+                .accounts
+                .oracle_registry
+                .as_ref()
+                .ok_or(ErrorCode::OraclePriceUnavailable)?;
+            let (decimals_in, decimals_out) =
+                get_pool_decimals(&*ctx.accounts.pool_state.load()?, token_in, token_out)?;
+            check_oracle_min_out(
+                token_in,
+                token_out,
+                decimals_in,
+                decimals_out,
+                amount_in,
+                amount_out_minimum,
+                ctx.accounts.vault.max_oracle_slippage_bps,
+                registry,
+                ctx.remaining_accounts,
+            )?;
+        }
+        
+        // 检查机器人交易限制 (金库限制和附加机器人自身的限制)
+        check_and_record_bot_trade(
+            &mut ctx.accounts.vault,
+            ctx.accounts.user.key(),
+            amount_in,
+            clock.slot,
+            clock.unix_timestamp,
+        )?;
+        
+        msg!("验证通过，开始交换...");
+        msg!("当前输入代币余额: {}", current_balance);
+        
+        // 扣除输入代币
+        set_token_balance(&mut ctx.accounts.vault, token_in, current_balance - amount_in);
+        
+        // 直接调用 Byreal CLMM CPI 进行交换
+        let amount_out = execute_byreal_swap_cpi(&ctx, amount_in, amount_out_minimum)?;
+        
+        msg!("交换完成，输出金额: {}", amount_out);
+        require!(amount_out >= amount_out_minimum, ErrorCode::InsufficientOutputAmount);
+        
+        // 计算费用
+        let (user_amount, fee_amount) = split_trade_fee(amount_out, fee_rate)?;
+        
+        msg!("费用金额: {}", fee_amount);
+        msg!("用户获得金额: {}", user_amount);
+        
+        // 更新输出代币余额
+        let current_out_balance = get_token_balance(&ctx.accounts.vault, token_out);
+        let new_out_balance = current_out_balance
+            .checked_add(user_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        set_token_balance(&mut ctx.accounts.vault, token_out, new_out_balance);
+        
+        // 如果有费用，转账给平台金库
+        transfer_trade_fee(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.output_token_account.to_account_info(),
+            &ctx.accounts.fee_recipient.to_account_info(),
+            &ctx.accounts.user.to_account_info(),
+            fee_amount,
+        )?;
+        
+        emit!(TradeSignalEvent {
+            user: ctx.accounts.user.key(),
+            from_asset_metadata: token_in, // 对应 Aptos 的 Object<Metadata>
+            to_asset_metadata: token_out,  // 对应 Aptos 的 Object<Metadata>
+            amount_in,
+            amount_out_min: amount_out_minimum,
+            amount_out,
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            fee_amount,
+            signal_id,
+            schedule_id: 0,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+        
+        msg!("交易信号发送完成!");
+        Ok(amount_out)
+}
+
+    /// 紧急卖出所有非基础资产 (对应 UniswapVault 的 emergencyExitAll)
+    /// remaining_accounts 按顺序为每个待卖出代币提供 8 个账户:
+    /// amm_config, pool_state, input_token_account, output_token_account,
+    /// input_vault, output_vault, observation_state, tick_array，
+    /// 之后是价格账户 (平台管理员调用且金库启用预言机检查时必须提供)
+    /// 平台管理员调用时每个最小输出必须大于 0，启用预言机检查时还不得低于预言机隐含输出扣除滑点
+    pub fn emergency_exit_all<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyExitAll<'info>>,
+        base_mint: Pubkey,
+        amount_out_minimums: Vec<u64>, // 每个卖出代币对应一个最小输出金额
+    ) -> Result<()> {
+        msg!("开始紧急退出操作...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("基础资产: {}", base_mint);
+
+        require!(
+            ctx.accounts.vault.has_role(VaultRole::Investor, &ctx.accounts.user.key())
+                || ctx.accounts.user.key() == ctx.accounts.config.authority,
+            ErrorCode::Unauthorized
+        );
+        require!(ctx.accounts.vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(base_mint != Pubkey::default(), ErrorCode::InvalidBaseMint);
+
+        // 收集所有需要卖出的非基础资产仓位
+        let positions: Vec<TokenBalance> = ctx
+            .accounts
+            .vault
+            .balances
+            .iter()
+            .filter(|balance| balance.token != base_mint && balance.amount > 0)
+            .cloned()
+            .collect();
+
+        msg!("需要卖出的仓位数量: {}", positions.len());
+
+        require!(amount_out_minimums.len() == positions.len(), ErrorCode::InvalidExitLegs);
+        require!(
+            ctx.remaining_accounts.len() >= positions.len() * EMERGENCY_EXIT_ACCOUNTS_PER_LEG,
+            ErrorCode::InvalidExitLegs
+        );
+        let (swap_legs, price_accounts) = ctx
+            .remaining_accounts
+            .split_at(positions.len() * EMERGENCY_EXIT_ACCOUNTS_PER_LEG);
+
+        // 投资者可以按任意价格退出自己的仓位，平台管理员的最小输出需要受到约束
+        let is_investor = ctx.accounts.vault.has_role(VaultRole::Investor, &ctx.accounts.user.key());
+        if !is_investor {
+            require!(
+                amount_out_minimums.iter().all(|minimum| *minimum > 0),
+                ErrorCode::InvalidAmount
+            );
+        }
+        let oracle_floor = !is_investor && ctx.accounts.vault.oracle_check_enabled;
+
+        let payer = ctx.accounts.user.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let mut total_amount_out: u64 = 0;
+
+        for (index, position) in positions.iter().enumerate() {
+            let leg = &swap_legs
+                [index * EMERGENCY_EXIT_ACCOUNTS_PER_LEG..(index + 1) * EMERGENCY_EXIT_ACCOUNTS_PER_LEG];
+
+            msg!("卖出第{}个仓位: {} 数量: {}", index + 1, position.token, position.amount);
+
+            validate_byreal_swap_leg(leg, position.token, base_mint)?;
+
+            let amount_out_minimum = amount_out_minimums[index];
+            if oracle_floor {
+                let registry = ctx
+                    .accounts
+                    .oracle_registry
+                    .as_ref()
+                    .ok_or(ErrorCode::OraclePriceUnavailable)?;
+                let (decimals_in, decimals_out) = {
+                    let pool_state = AccountLoader::<PoolState>::try_from(&leg[1])?;
+                    let pool = pool_state.load()?;
+                    get_pool_decimals(&pool, position.token, base_mint)?
+                };
+                check_oracle_min_out(
+                    position.token,
+                    base_mint,
+                    decimals_in,
+                    decimals_out,
+                    position.amount,
+                    amount_out_minimum,
+                    ctx.accounts.vault.max_oracle_slippage_bps,
+                    registry,
+                    price_accounts,
+                )?;
+            }
+
+            let swap_accounts = ByrealSwapAccounts {
+                payer: &payer,
+                amm_config: &leg[0],
+                pool_state: &leg[1],
+                input_token_account: &leg[2],
+                output_token_account: &leg[3],
+                input_vault: &leg[4],
+                output_vault: &leg[5],
+                observation_state: &leg[6],
+                token_program: &token_program,
+                tick_array: &leg[7],
+            };
+
+            let amount_out = invoke_byreal_swap(swap_accounts, position.amount, amount_out_minimum)?;
+            require!(amount_out >= amount_out_minimum, ErrorCode::InsufficientOutputAmount);
+
+            set_token_balance(&mut ctx.accounts.vault, position.token, 0);
+            total_amount_out = total_amount_out
+                .checked_add(amount_out)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        // 更新基础资产余额
+        let current_base_balance = get_token_balance(&ctx.accounts.vault, base_mint);
+        let new_base_balance = current_base_balance
+            .checked_add(total_amount_out)
+            .ok_or(ErrorCode::MathOverflow)?;
+        set_token_balance(&mut ctx.accounts.vault, base_mint, new_base_balance);
+
+        // 退出后禁用策略
+        ctx.accounts.vault.strategy_enabled = false;
+
+        let timestamp_microseconds = Clock::get()?.unix_timestamp as u64 * 1_000_000; // 转换为微秒
+
+        emit!(EmergencyExitEvent {
+            user: ctx.accounts.user.key(),
+            base_asset_metadata: base_mint,
+            positions_exited: positions.len() as u32,
+            total_amount_out,
+            timestamp_microseconds,
+        });
+
+        emit!(StrategyStatusChangedEvent {
+            enabled: false,
+            changed_by: ctx.accounts.user.key(),
+            timestamp_microseconds,
+        });
+
+        msg!("紧急退出操作完成，基础资产总输出: {}", total_amount_out);
+        Ok(())
+    }
+
+    /// 挂限价单 (投资者或拥有交换权限的机器人)
+    /// 机器人挂的订单在成交时按 send_trade_signal 的规则检查机器人权限、交易限制和预言机下限；
+    /// 金库启用签名交易意图模式时机器人不能挂单
+    pub fn place_limit_order(
+        ctx: Context<PlaceLimitOrder>,
+        order_id: u64,
+        token_in: Pubkey,
+        token_out: Pubkey,
+        amount_in: u64,
+        min_amount_out: u64, // 限价 P * 卖出数量
+        expiry: i64,         // 过期时间戳 (秒)
+    ) -> Result<()> {
+        msg!("开始挂限价单...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("订单编号: {}", order_id);
+        msg!("卖出代币: {} 数量: {}", token_in, amount_in);
+        msg!("买入代币: {} 最小数量: {}", token_out, min_amount_out);
+        msg!("过期时间戳: {}", expiry);
+
+        let vault = &ctx.accounts.vault;
+        let user = ctx.accounts.user.key();
+
+        if !vault.has_role(VaultRole::Investor, &user) {
+            require_bot_permission(vault, user, BOT_PERMISSION_SWAP)?;
+            require!(vault.strategy_enabled, ErrorCode::StrategyDisabled);
+            // 签名意图只能约束即时交易，挂单会绕过投资者签名
+            require!(!vault.require_signed_intents, ErrorCode::MissingSignedIntent);
+        }
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(amount_in > 0 && min_amount_out > 0, ErrorCode::InvalidAmount);
+        require!(token_in != token_out, ErrorCode::InvalidSwapAccounts);
+        require!(expiry > Clock::get()?.unix_timestamp, ErrorCode::InvalidOrderExpiry);
+
+        let order = &mut ctx.accounts.order;
+        order.vault = vault.key();
+        order.order_id = order_id;
+        order.placed_by = user;
+        order.token_in = token_in;
+        order.token_out = token_out;
+        order.amount_in = amount_in;
+        order.min_amount_out = min_amount_out;
+        order.expiry = expiry;
+        order.bump = ctx.bumps.order;
+
+        msg!("限价单创建完成!");
+        msg!("订单地址: {}", ctx.accounts.order.key());
+
+        emit!(LimitOrderPlacedEvent {
+            vault: vault.key(),
+            order_id,
+            placed_by: user,
+            token_in,
+            token_out,
+            amount_in,
+            min_amount_out,
+            expiry,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 执行限价单 (任何 keeper 都可以调用)，Byreal 池子现价满足限价时通过交换路径成交
+    /// 机器人挂的订单还要求机器人仍有交换权限，并记入金库和机器人的交易限制；
+    /// 金库启用预言机检查时限价不得低于预言机隐含输出扣除滑点
+    /// remaining_accounts 依次为一组 Byreal 交换账户 (见 BYREAL_SWAP_LEG_ACCOUNTS) 和价格账户 (机器人订单且启用预言机检查时)
+    pub fn execute_limit_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteLimitOrder<'info>>,
+    ) -> Result<()> {
+        msg!("开始执行限价单...");
+        msg!("Keeper 地址: {}", ctx.accounts.keeper.key());
+        msg!("订单编号: {}", ctx.accounts.order.order_id);
+
+        let order = &ctx.accounts.order;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        require!(!ctx.accounts.config.paused, ErrorCode::ProtocolPaused);
+        require!(ctx.accounts.vault.strategy_enabled, ErrorCode::StrategyDisabled);
+        require!(now <= order.expiry, ErrorCode::OrderExpired);
+        require!(
+            ctx.remaining_accounts.len() >= BYREAL_SWAP_LEG_ACCOUNTS,
+            ErrorCode::InvalidSwapAccounts
+        );
+        let (leg, price_accounts) = ctx.remaining_accounts.split_at(BYREAL_SWAP_LEG_ACCOUNTS);
+
+        // 检查池子现价是否满足限价
+        let spot_amount_out = {
+            let pool_state = AccountLoader::<PoolState>::try_from(&leg[1])?;
+            let pool = pool_state.load()?;
+            pool_spot_amount_out(&pool, order.token_in, order.token_out, order.amount_in)?
+        };
+        msg!("池子现价可兑换: {}，限价要求: {}", spot_amount_out, order.min_amount_out);
+        require!(spot_amount_out >= order.min_amount_out, ErrorCode::LimitPriceNotReached);
+
+        // 机器人挂的订单按 send_trade_signal 的规则检查
+        if !ctx.accounts.vault.has_role(VaultRole::Investor, &order.placed_by) {
+            msg!("机器人订单，检查机器人权限与交易限制");
+            require_bot_permission(&ctx.accounts.vault, order.placed_by, BOT_PERMISSION_SWAP)?;
+            if ctx.accounts.vault.oracle_check_enabled {
+                let registry = ctx
+                    .accounts
+                    .oracle_registry
+                    .as_ref()
+                    .ok_or(ErrorCode::OraclePriceUnavailable)?;
+                let (decimals_in, decimals_out) = {
+                    let pool_state = AccountLoader::<PoolState>::try_from(&leg[1])?;
+                    let pool = pool_state.load()?;
+                    get_pool_decimals(&pool, order.token_in, order.token_out)?
+                };
+                check_oracle_min_out(
+                    order.token_in,
+                    order.token_out,
+                    decimals_in,
+                    decimals_out,
+                    order.amount_in,
+                    order.min_amount_out,
+                    ctx.accounts.vault.max_oracle_slippage_bps,
+                    registry,
+                    price_accounts,
+                )?;
+            }
+            check_and_record_bot_trade(
+                &mut ctx.accounts.vault,
+                order.placed_by,
+                order.amount_in,
+                clock.slot,
+                now,
+            )?;
+        }
+
+        let fee_rate = vault_fee_rate(&ctx.accounts.vault, &ctx.accounts.config)?;
+        let payer = ctx.accounts.keeper.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let fee_recipient = ctx.accounts.fee_recipient.to_account_info();
+        let (amount_out, fee_amount) = execute_vault_swap(
+            &mut ctx.accounts.vault,
+            leg,
+            &payer,
+            &token_program,
+            &fee_recipient,
+            order.token_in,
+            order.token_out,
+            order.amount_in,
+            order.min_amount_out,
+            fee_rate,
+        )?;
+
+        msg!("限价单成交完成!");
+
+        emit!(LimitOrderExecutedEvent {
+            vault: ctx.accounts.vault.key(),
+            order_id: order.order_id,
+            keeper: ctx.accounts.keeper.key(),
+            amount_in: order.amount_in,
+            amount_out,
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            fee_amount,
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 挂条件单 (仅投资者)，触发时不经过机器人的交易限制和签名意图检查，因此机器人不能挂单
+    /// 追踪止损以 trigger_price 作为初始最高价
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_conditional_order(
+        ctx: Context<PlaceConditionalOrder>,
+        order_id: u64,
+        kind: ConditionalOrderKind,
+        token_in: Pubkey,
+        token_out: Pubkey,
+        amount_in: u64,
+        trigger_price: u128,
+        trailing_bps: u16,
+        expiry: i64,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        msg!("开始挂条件单...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("订单编号: {}", order_id);
+        msg!("条件单类型: {:?}", kind);
+        msg!("卖出代币: {} 数量: {}", token_in, amount_in);
+        msg!("买入代币: {}", token_out);
+        msg!("触发价: {}", trigger_price);
+        msg!("回落比例 (基点): {}", trailing_bps);
+        msg!("过期时间戳: {}", expiry);
+        msg!("最大滑点 (基点): {}", max_slippage_bps);
+
+        let vault = &ctx.accounts.vault;
+        let user = ctx.accounts.user.key();
+
+        require_vault_role(vault, user, &[VaultRole::Investor])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(amount_in > 0, ErrorCode::InvalidAmount);
+        require!(token_in != token_out, ErrorCode::InvalidSwapAccounts);
+        require!(trigger_price > 0, ErrorCode::InvalidTriggerPrice);
+        if kind == ConditionalOrderKind::TrailingStop {
+            require!(
+                trailing_bps > 0 && (trailing_bps as u64) < oracle::BPS_DENOMINATOR,
+                ErrorCode::InvalidTriggerPrice
+            );
+        }
+        require!(
+            expiry == 0 || expiry > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidOrderExpiry
+        );
+        validate_order_slippage_bps(max_slippage_bps)?;
+
+        let order = &mut ctx.accounts.order;
+        order.vault = vault.key();
+        order.order_id = order_id;
+        order.placed_by = user;
+        order.kind = kind;
+        order.token_in = token_in;
+        order.token_out = token_out;
+        order.amount_in = amount_in;
+        order.trigger_price = trigger_price;
+        order.trailing_bps = trailing_bps;
+        order.peak_price = trigger_price;
+        order.expiry = expiry;
+        order.max_slippage_bps = max_slippage_bps;
+        order.bump = ctx.bumps.order;
+
+        msg!("条件单创建完成!");
+        msg!("订单地址: {}", ctx.accounts.order.key());
+
+        emit!(ConditionalOrderPlacedEvent {
+            vault: vault.key(),
+            order_id,
+            placed_by: user,
+            kind,
+            token_in,
+            token_out,
+            amount_in,
+            trigger_price,
+            trailing_bps,
+            expiry,
+            max_slippage_bps,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 触发条件单 (任何 keeper 都可以调用)，按预言机价格判断是否触发
+    /// 触发时通过交换路径卖出，最小输出为预言机隐含输出扣除订单配置的滑点；
+    /// 追踪止损未触发但价格创新高时只更新最高价
+    /// remaining_accounts 依次为一组 Byreal 交换账户 (见 BYREAL_SWAP_LEG_ACCOUNTS) 和价格账户
+    pub fn crank_conditional_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CrankConditionalOrder<'info>>,
+    ) -> Result<()> {
+        msg!("开始检查条件单...");
+        msg!("Keeper 地址: {}", ctx.accounts.keeper.key());
+        msg!("订单编号: {}", ctx.accounts.order.order_id);
+
+        let now = Clock::get()?.unix_timestamp;
+        let order = &ctx.accounts.order;
+
+        require!(!ctx.accounts.config.paused, ErrorCode::ProtocolPaused);
+        require!(ctx.accounts.vault.strategy_enabled, ErrorCode::StrategyDisabled);
+        require!(order.expiry == 0 || now <= order.expiry, ErrorCode::OrderExpired);
+        require!(
+            ctx.remaining_accounts.len() >= BYREAL_SWAP_LEG_ACCOUNTS,
+            ErrorCode::InvalidSwapAccounts
+        );
+        let (leg, price_accounts) = ctx.remaining_accounts.split_at(BYREAL_SWAP_LEG_ACCOUNTS);
+
+        // 读取预言机价格
+        let registry = &ctx.accounts.oracle_registry;
+        let price_in = get_oracle_price(registry, order.token_in, price_accounts, now)?;
+        let price_out = get_oracle_price(registry, order.token_out, price_accounts, now)?;
+        let price = oracle::relative_precision_price(&price_in, &price_out)
+            .ok_or(ErrorCode::InvalidOraclePrice)?;
+
+        msg!("当前价格: {}", price);
+
+        let order = &mut ctx.accounts.order;
+        let mut peak_updated = false;
+        let triggered = match order.kind {
+            ConditionalOrderKind::StopLoss => price <= order.trigger_price,
+            ConditionalOrderKind::TakeProfit => price >= order.trigger_price,
+            ConditionalOrderKind::TrailingStop => {
+                if price > order.peak_price {
+                    order.peak_price = price;
+                    peak_updated = true;
+                }
+                let stop_price = order.peak_price
+                    / oracle::BPS_DENOMINATOR as u128
+                    * (oracle::BPS_DENOMINATOR - order.trailing_bps as u64) as u128;
+                msg!("最高价: {}，止损价: {}", order.peak_price, stop_price);
+                price <= stop_price
+            }
+        };
+
+        if !triggered {
+            require!(peak_updated, ErrorCode::TriggerNotReached);
+            msg!("条件单未触发，更新最高价: {}", order.peak_price);
+
+            emit!(ConditionalOrderPeakUpdatedEvent {
+                vault: order.vault,
+                order_id: order.order_id,
+                peak_price: order.peak_price,
+                timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+            });
+            return Ok(());
+        }
+
+        msg!("条件单已触发，开始卖出...");
+
+        // 预言机隐含输出扣除订单配置的滑点作为最小输出
+        let amount_out_minimum = oracle_amount_out_minimum(
+            &leg[1],
+            order.token_in,
+            order.token_out,
+            order.amount_in,
+            &price_in,
+            &price_out,
+            order.max_slippage_bps,
+        )?;
+
+        let fee_rate = vault_fee_rate(&ctx.accounts.vault, &ctx.accounts.config)?;
+        let payer = ctx.accounts.keeper.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let fee_recipient = ctx.accounts.fee_recipient.to_account_info();
+        let (amount_out, fee_amount) = execute_vault_swap(
+            &mut ctx.accounts.vault,
+            leg,
+            &payer,
+            &token_program,
+            &fee_recipient,
+            order.token_in,
+            order.token_out,
+            order.amount_in,
+            amount_out_minimum,
+            fee_rate,
+        )?;
+
+        msg!("条件单成交完成!");
+
+        emit!(ConditionalOrderExecutedEvent {
+            vault: order.vault,
+            order_id: order.order_id,
+            kind: order.kind,
+            keeper: ctx.accounts.keeper.key(),
+            trigger_price: price,
+            amount_in: order.amount_in,
+            amount_out,
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            fee_amount,
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        // 成交后关闭订单并退还租金
+        ctx.accounts.order.close(ctx.accounts.placed_by.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// 取消条件单：投资者或下单者随时可以取消，订单过期后任何人都可以取消
+    pub fn cancel_conditional_order(ctx: Context<CancelConditionalOrder>) -> Result<()> {
+        msg!("开始取消条件单...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("订单编号: {}", ctx.accounts.order.order_id);
+
+        let order = &ctx.accounts.order;
+        let user = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            user == order.placed_by
+                || ctx.accounts.vault.has_role(VaultRole::Investor, &user)
+                || (order.expiry > 0 && now > order.expiry),
+            ErrorCode::Unauthorized
+        );
+
+        msg!("条件单已取消!");
+
+        emit!(ConditionalOrderCancelledEvent {
+            vault: ctx.accounts.vault.key(),
+            order_id: order.order_id,
+            cancelled_by: user,
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 创建定投计划 (仅投资者)，第一次执行时间为 start_at
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_dca_schedule(
+        ctx: Context<CreateDcaSchedule>,
+        schedule_id: u64,
+        token_in: Pubkey,
+        token_out: Pubkey,
+        amount_per_execution: u64,
+        interval_seconds: i64,
+        total_executions: u32,
+        start_at: i64,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        msg!("开始创建定投计划...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("计划编号: {}", schedule_id);
+        msg!("卖出代币: {} 每次数量: {}", token_in, amount_per_execution);
+        msg!("买入代币: {}", token_out);
+        msg!("执行间隔 (秒): {}，总次数: {}", interval_seconds, total_executions);
+        msg!("首次执行时间戳: {}", start_at);
+        msg!("最大滑点 (基点): {}", max_slippage_bps);
+
+        let vault = &ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(amount_per_execution > 0, ErrorCode::InvalidAmount);
+        require!(token_in != token_out, ErrorCode::InvalidSwapAccounts);
+        require!(
+            interval_seconds > 0 && total_executions > 0,
+            ErrorCode::InvalidDcaSchedule
+        );
+        validate_order_slippage_bps(max_slippage_bps)?;
+
+        let schedule = &mut ctx.accounts.schedule;
+        schedule.vault = vault.key();
+        schedule.schedule_id = schedule_id;
+        schedule.created_by = ctx.accounts.user.key();
+        schedule.token_in = token_in;
+        schedule.token_out = token_out;
+        schedule.amount_per_execution = amount_per_execution;
+        schedule.interval_seconds = interval_seconds;
+        schedule.total_executions = total_executions;
+        schedule.executions_done = 0;
+        schedule.next_execution_at = start_at;
+        schedule.max_slippage_bps = max_slippage_bps;
+        schedule.bump = ctx.bumps.schedule;
+
+        msg!("定投计划创建完成!");
+        msg!("计划地址: {}", ctx.accounts.schedule.key());
+
+        emit!(DcaScheduleCreatedEvent {
+            vault: vault.key(),
+            schedule_id,
+            token_in,
+            token_out,
+            amount_per_execution,
+            interval_seconds,
+            total_executions,
+            start_at,
+            max_slippage_bps,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 执行一次到期的定投 (任何 keeper 都可以调用)，最小输出为预言机隐含输出扣除计划配置的滑点
+    /// 最后一次执行后关闭计划账户
+    /// remaining_accounts 依次为一组 Byreal 交换账户 (见 BYREAL_SWAP_LEG_ACCOUNTS) 和价格账户
+    pub fn execute_dca<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteDca<'info>>,
+    ) -> Result<()> {
+        msg!("开始执行定投...");
+        msg!("Keeper 地址: {}", ctx.accounts.keeper.key());
+        msg!("计划编号: {}", ctx.accounts.schedule.schedule_id);
+
+        let now = Clock::get()?.unix_timestamp;
+        let schedule = &ctx.accounts.schedule;
+
+        require!(!ctx.accounts.config.paused, ErrorCode::ProtocolPaused);
+        require!(ctx.accounts.vault.strategy_enabled, ErrorCode::StrategyDisabled);
+        require!(now >= schedule.next_execution_at, ErrorCode::DcaNotDue);
+        require!(
+            ctx.remaining_accounts.len() >= BYREAL_SWAP_LEG_ACCOUNTS,
+            ErrorCode::InvalidSwapAccounts
+        );
+        let (leg, price_accounts) = ctx.remaining_accounts.split_at(BYREAL_SWAP_LEG_ACCOUNTS);
+
+        msg!("第 {} / {} 次执行", schedule.executions_done + 1, schedule.total_executions);
+
+        let registry = &ctx.accounts.oracle_registry;
+        let price_in = get_oracle_price(registry, schedule.token_in, price_accounts, now)?;
+        let price_out = get_oracle_price(registry, schedule.token_out, price_accounts, now)?;
+        let amount_out_minimum = oracle_amount_out_minimum(
+            &leg[1],
+            schedule.token_in,
+            schedule.token_out,
+            schedule.amount_per_execution,
+            &price_in,
+            &price_out,
+            schedule.max_slippage_bps,
+        )?;
+
+        let fee_rate = vault_fee_rate(&ctx.accounts.vault, &ctx.accounts.config)?;
+        let payer = ctx.accounts.keeper.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let fee_recipient = ctx.accounts.fee_recipient.to_account_info();
+        let (amount_out, fee_amount) = execute_vault_swap(
+            &mut ctx.accounts.vault,
+            leg,
+            &payer,
+            &token_program,
+            &fee_recipient,
+            schedule.token_in,
+            schedule.token_out,
+            schedule.amount_per_execution,
+            amount_out_minimum,
+            fee_rate,
+        )?;
+
+        let schedule = &mut ctx.accounts.schedule;
+        schedule.executions_done += 1;
+        schedule.next_execution_at = now
+            .checked_add(schedule.interval_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // 定投执行按交易信号记录，signal_id 为本次执行序号
+        emit!(TradeSignalEvent {
+            user: ctx.accounts.keeper.key(),
+            from_asset_metadata: schedule.token_in, // 对应 Aptos 的 Object<Metadata>
+            to_asset_metadata: schedule.token_out,  // 对应 Aptos 的 Object<Metadata>
+            amount_in: schedule.amount_per_execution,
+            amount_out_min: amount_out_minimum,
+            amount_out,
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            fee_amount,
+            signal_id: schedule.executions_done as u64,
+            schedule_id: schedule.schedule_id,
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        if schedule.executions_done >= schedule.total_executions {
+            msg!("定投计划已全部执行，关闭计划账户");
+            ctx.accounts.schedule.close(ctx.accounts.created_by.to_account_info())?;
+        } else {
+            msg!("下次可执行时间戳: {}", schedule.next_execution_at);
+        }
+
+        msg!("定投执行完成!");
+        Ok(())
+    }
+
+    /// 取消定投计划 (仅投资者)，关闭计划账户并退还租金
+    pub fn cancel_dca_schedule(ctx: Context<CancelDcaSchedule>) -> Result<()> {
+        msg!("开始取消定投计划...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("计划编号: {}", ctx.accounts.schedule.schedule_id);
+
+        require_vault_role(&ctx.accounts.vault, ctx.accounts.user.key(), &[VaultRole::Investor])?;
+
+        msg!(
+            "已执行次数: {} / {}",
+            ctx.accounts.schedule.executions_done,
+            ctx.accounts.schedule.total_executions
+        );
+        msg!("定投计划已取消!");
+
+        emit!(DcaScheduleCancelledEvent {
+            vault: ctx.accounts.vault.key(),
+            schedule_id: ctx.accounts.schedule.schedule_id,
+            executions_done: ctx.accounts.schedule.executions_done,
+            cancelled_by: ctx.accounts.user.key(),
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 挂 TWAP 订单 (仅投资者)
+    /// 滑点由挂单者决定且子交换不经过机器人的交易限制检查，因此机器人不能挂单
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_twap_order(
+        ctx: Context<PlaceTwapOrder>,
+        order_id: u64,
+        token_in: Pubkey,
+        token_out: Pubkey,
+        total_amount_in: u64,
+        slice_amount: u64,
+        min_interval_slots: u64,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        msg!("开始挂 TWAP 订单...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("订单编号: {}", order_id);
+        msg!("卖出代币: {} 总数量: {} 每份数量: {}", token_in, total_amount_in, slice_amount);
+        msg!("买入代币: {}", token_out);
+        msg!("最小间隔 (slot): {}", min_interval_slots);
+        msg!("最大滑点 (基点): {}", max_slippage_bps);
+
+        let vault = &ctx.accounts.vault;
+        let user = ctx.accounts.user.key();
+
+        require_vault_role(vault, user, &[VaultRole::Investor])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(token_in != token_out, ErrorCode::InvalidSwapAccounts);
+        validate_twap_order(total_amount_in, slice_amount, min_interval_slots, max_slippage_bps)?;
+
+        let order = &mut ctx.accounts.order;
+        order.vault = vault.key();
+        order.order_id = order_id;
+        order.placed_by = user;
+        order.token_in = token_in;
+        order.token_out = token_out;
+        order.total_amount_in = total_amount_in;
+        order.slice_amount = slice_amount;
+        order.min_interval_slots = min_interval_slots;
+        order.max_slippage_bps = max_slippage_bps;
+        order.amount_filled = 0;
+        order.amount_out_total = 0;
+        order.slices_executed = 0;
+        order.last_execution_slot = 0;
+        order.bump = ctx.bumps.order;
+
+        msg!("TWAP 订单创建完成!");
+        msg!("订单地址: {}", ctx.accounts.order.key());
+
+        emit!(TwapOrderPlacedEvent {
+            vault: vault.key(),
+            order_id,
+            placed_by: user,
+            token_in,
+            token_out,
+            total_amount_in,
+            slice_amount,
+            min_interval_slots,
+            max_slippage_bps,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 执行 TWAP 订单的下一个子交换 (任何 keeper 都可以调用)
+    /// 每个子交换的最小输出为预言机隐含输出扣除订单的滑点，全部成交后关闭订单账户
+    /// remaining_accounts 依次为一组 Byreal 交换账户 (见 BYREAL_SWAP_LEG_ACCOUNTS) 和价格账户
+    pub fn execute_twap_slice<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteTwapSlice<'info>>,
+    ) -> Result<()> {
+        msg!("开始执行 TWAP 子交换...");
+        msg!("Keeper 地址: {}", ctx.accounts.keeper.key());
+        msg!("订单编号: {}", ctx.accounts.order.order_id);
+
+        let clock = Clock::get()?;
+        let order = &ctx.accounts.order;
+
+        require!(!ctx.accounts.config.paused, ErrorCode::ProtocolPaused);
+        require!(ctx.accounts.vault.strategy_enabled, ErrorCode::StrategyDisabled);
+        if order.slices_executed > 0 {
+            require!(
+                clock.slot >= order.last_execution_slot.saturating_add(order.min_interval_slots),
+                ErrorCode::TwapSliceNotDue
+            );
+        }
+        require!(
+            ctx.remaining_accounts.len() >= BYREAL_SWAP_LEG_ACCOUNTS,
+            ErrorCode::InvalidSwapAccounts
+        );
+        let (leg, price_accounts) = ctx.remaining_accounts.split_at(BYREAL_SWAP_LEG_ACCOUNTS);
+
+        let amount_in = order.slice_amount.min(order.total_amount_in - order.amount_filled);
+        msg!("第 {} 个子交换，卖出数量: {}", order.slices_executed + 1, amount_in);
+
+        let registry = &ctx.accounts.oracle_registry;
+        let price_in = get_oracle_price(registry, order.token_in, price_accounts, clock.unix_timestamp)?;
+        let price_out = get_oracle_price(registry, order.token_out, price_accounts, clock.unix_timestamp)?;
+        let amount_out_minimum = oracle_amount_out_minimum(
+            &leg[1],
+            order.token_in,
+            order.token_out,
+            amount_in,
+            &price_in,
+            &price_out,
+            order.max_slippage_bps,
+        )?;
+
+        let fee_rate = vault_fee_rate(&ctx.accounts.vault, &ctx.accounts.config)?;
+        let payer = ctx.accounts.keeper.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let fee_recipient = ctx.accounts.fee_recipient.to_account_info();
+        let (amount_out, fee_amount) = execute_vault_swap(
+            &mut ctx.accounts.vault,
+            leg,
+            &payer,
+            &token_program,
+            &fee_recipient,
+            order.token_in,
+            order.token_out,
+            amount_in,
+            amount_out_minimum,
+            fee_rate,
+        )?;
+
+        let order = &mut ctx.accounts.order;
+        order.amount_filled += amount_in;
+        order.amount_out_total = order
+            .amount_out_total
+            .checked_add(amount_out)
+            .ok_or(ErrorCode::MathOverflow)?;
+        order.slices_executed += 1;
+        order.last_execution_slot = clock.slot;
+
+        msg!("已卖出: {} / {}", order.amount_filled, order.total_amount_in);
+
+        emit!(TwapSliceExecutedEvent {
+            vault: order.vault,
+            order_id: order.order_id,
+            slice_index: order.slices_executed,
+            keeper: ctx.accounts.keeper.key(),
+            amount_in,
+            amount_out_minimum,
+            amount_out,
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            fee_amount,
+            amount_filled: order.amount_filled,
+            timestamp_microseconds: clock.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        if order.amount_filled >= order.total_amount_in {
+            msg!("TWAP 订单已全部成交，关闭订单账户");
+            ctx.accounts.order.close(ctx.accounts.placed_by.to_account_info())?;
+        }
+
+        msg!("TWAP 子交换执行完成!");
+        Ok(())
+    }
+
+    /// 取消 TWAP 订单 (投资者或下单者)，关闭订单账户并退还租金
+    pub fn cancel_twap_order(ctx: Context<CancelTwapOrder>) -> Result<()> {
+        msg!("开始取消 TWAP 订单...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("订单编号: {}", ctx.accounts.order.order_id);
+
+        let order = &ctx.accounts.order;
+        let user = ctx.accounts.user.key();
+
+        require!(
+            user == order.placed_by || ctx.accounts.vault.has_role(VaultRole::Investor, &user),
+            ErrorCode::Unauthorized
+        );
+
+        msg!("已卖出: {} / {}", order.amount_filled, order.total_amount_in);
+        msg!("TWAP 订单已取消!");
+
+        emit!(TwapOrderCancelledEvent {
+            vault: ctx.accounts.vault.key(),
+            order_id: order.order_id,
+            amount_filled: order.amount_filled,
+            cancelled_by: user,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 设置再平衡目标权重 (仅投资者)
+    pub fn set_target_weights(
+        ctx: Context<SetTargetWeights>,
+        base_mint: Pubkey,
+        tolerance_bps: u16,
+        max_slippage_bps: u16,
+        targets: Vec<TargetWeight>,
+    ) -> Result<()> {
+        msg!("开始设置再平衡目标权重...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("基础资产: {}", base_mint);
+        msg!("容忍带宽 (基点): {}", tolerance_bps);
+        msg!("最大滑点 (基点): {}", max_slippage_bps);
+
+        let vault = &ctx.accounts.vault;
+
+        require_vault_role(vault, ctx.accounts.user.key(), &[VaultRole::Investor])?;
+        require!(vault.is_initialized, ErrorCode::VaultNotInitialized);
+        require!(
+            !targets.is_empty() && targets.len() <= MAX_TARGET_WEIGHTS,
+            ErrorCode::InvalidTargetWeights
+        );
+        require!(
+            (tolerance_bps as u64) <= oracle::BPS_DENOMINATOR,
+            ErrorCode::InvalidSlippageBps
+        );
+        validate_order_slippage_bps(max_slippage_bps)?;
+
+        let mut total_weight: u64 = 0;
+        for (index, target) in targets.iter().enumerate() {
+            require!(
+                targets[..index].iter().all(|other| other.mint != target.mint),
+                ErrorCode::InvalidTargetWeights
+            );
+            msg!("代币: {} 目标权重 (基点): {}", target.mint, target.weight_bps);
+            total_weight += target.weight_bps as u64;
+        }
+        require!(total_weight == oracle::BPS_DENOMINATOR, ErrorCode::InvalidTargetWeights);
+
+        let rebalance_targets = &mut ctx.accounts.rebalance_targets;
+        rebalance_targets.vault = vault.key();
+        rebalance_targets.base_mint = base_mint;
+        rebalance_targets.tolerance_bps = tolerance_bps;
+        rebalance_targets.max_slippage_bps = max_slippage_bps;
+        rebalance_targets.targets = targets.clone();
+        rebalance_targets.bump = ctx.bumps.rebalance_targets;
+
+        msg!("再平衡目标权重设置完成!");
+
+        emit!(TargetWeightsUpdatedEvent {
+            vault: vault.key(),
+            base_mint,
+            tolerance_bps,
+            max_slippage_bps,
+            targets,
+            timestamp_microseconds: Clock::get()?.unix_timestamp as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 执行一步再平衡 (投资者或拥有再平衡权限的机器人)
+    /// 按预言机净值计算各代币相对目标权重的偏离，只有组合偏离超出容忍带宽时才允许交易；
+    /// token_in 必须高于目标权重、token_out 必须低于目标权重，卖出价值取两者偏离中较小的一个，
+    /// 最小输出为预言机隐含输出扣除目标账户的 max_slippage_bps。机器人调用时与 send_trade_signal 一样
+    /// 记入金库和机器人的交易限制。可重复调用直到回到容忍带宽内
+    /// remaining_accounts 依次为一组 Byreal 交换账户 (见 BYREAL_SWAP_LEG_ACCOUNTS)，
+    /// 以及各持仓代币的价格账户与代币 (Mint) 账户
+    pub fn rebalance_step<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RebalanceStep<'info>>,
+        token_in: Pubkey,
+        token_out: Pubkey,
+    ) -> Result<()> {
+        msg!("开始执行再平衡...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("卖出代币: {}", token_in);
+        msg!("买入代币: {}", token_out);
+
+        let vault = &ctx.accounts.vault;
+        let user = ctx.accounts.user.key();
+
+        if !vault.has_role(VaultRole::Investor, &user) {
+            require_bot_permission(vault, user, BOT_PERMISSION_REBALANCE)?;
+        }
+        require!(!ctx.accounts.config.paused, ErrorCode::ProtocolPaused);
+        require!(vault.strategy_enabled, ErrorCode::StrategyDisabled);
+        require!(token_in != token_out, ErrorCode::InvalidSwapAccounts);
+        require!(
+            ctx.remaining_accounts.len() >= BYREAL_SWAP_LEG_ACCOUNTS,
+            ErrorCode::InvalidSwapAccounts
+        );
+        let (leg, accounts) = ctx.remaining_accounts.split_at(BYREAL_SWAP_LEG_ACCOUNTS);
+
+        let registry = &ctx.accounts.oracle_registry;
+        let rebalance_targets = &ctx.accounts.rebalance_targets;
+        let composition = value_portfolio(vault, registry, rebalance_targets.base_mint, accounts)?;
+        require!(composition.total_value > 0, ErrorCode::InsufficientBalance);
+
+        // 以基础资产计的目标价值，未列出的代币目标为 0
+        let target_value = |mint: Pubkey| -> u64 {
+            let weight_bps = rebalance_targets
+                .targets
+                .iter()
+                .find(|target| target.mint == mint)
+                .map_or(0, |target| target.weight_bps);
+            ((composition.total_value as u128) * (weight_bps as u128)
+                / (oracle::BPS_DENOMINATOR as u128)) as u64
+        };
+        let current_value = |mint: Pubkey| -> u64 {
+            composition
+                .tokens
+                .iter()
+                .find(|token| token.token == mint)
+                .map_or(0, |token| token.value)
+        };
+
+        // 组合中偏离最大的代币必须超出容忍带宽
+        let max_deviation_bps = composition
+            .tokens
+            .iter()
+            .map(|token| token.token)
+            .chain(rebalance_targets.targets.iter().map(|target| target.mint))
+            .map(|mint| {
+                (current_value(mint).abs_diff(target_value(mint)) as u128)
+                    * (oracle::BPS_DENOMINATOR as u128)
+                    / (composition.total_value as u128)
+            })
+            .max()
+            .unwrap_or_default() as u64;
+        msg!("最大权重偏离 (基点): {}", max_deviation_bps);
+        require!(
+            max_deviation_bps > rebalance_targets.tolerance_bps as u64,
+            ErrorCode::PortfolioWithinTolerance
+        );
+
+        let excess = current_value(token_in).saturating_sub(target_value(token_in));
+        let shortfall = target_value(token_out).saturating_sub(current_value(token_out));
+        require!(excess > 0 && shortfall > 0, ErrorCode::InvalidRebalancePair);
+        let trade_value = excess.min(shortfall);
+
+        // 按持仓数量与价值的比例把卖出价值换算为 token_in 数量
+        let balance_in = get_token_balance(vault, token_in);
+        let amount_in = ((balance_in as u128) * (trade_value as u128)
+            / (current_value(token_in) as u128)) as u64;
+        require!(amount_in > 0, ErrorCode::InvalidAmount);
+        msg!("卖出价值: {} 卖出数量: {}", trade_value, amount_in);
+
+        let now = Clock::get()?.unix_timestamp;
+        let price_in = get_oracle_price(registry, token_in, accounts, now)?;
+        let price_out = get_oracle_price(registry, token_out, accounts, now)?;
+        let amount_out_minimum = oracle_amount_out_minimum(
+            &leg[1],
+            token_in,
+            token_out,
+            amount_in,
+            &price_in,
+            &price_out,
+            rebalance_targets.max_slippage_bps,
+        )?;
+
+        // 机器人再平衡同样受金库和机器人自身的交易限制约束
+        if !vault.has_role(VaultRole::Investor, &user) {
+            let clock = Clock::get()?;
+            check_and_record_bot_trade(&mut ctx.accounts.vault, user, amount_in, clock.slot, now)?;
+        }
+
+        let fee_rate = vault_fee_rate(&ctx.accounts.vault, &ctx.accounts.config)?;
+        let payer = ctx.accounts.user.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let fee_recipient = ctx.accounts.fee_recipient.to_account_info();
+        let (amount_out, fee_amount) = execute_vault_swap(
+            &mut ctx.accounts.vault,
+            leg,
+            &payer,
+            &token_program,
+            &fee_recipient,
+            token_in,
+            token_out,
+            amount_in,
+            amount_out_minimum,
+            fee_rate,
+        )?;
+
+        msg!("再平衡交易完成，买入数量: {}", amount_out);
+
+        emit!(RebalanceStepEvent {
+            vault: ctx.accounts.vault.key(),
+            executed_by: user,
+            token_in,
+            token_out,
+            amount_in,
+            amount_out_minimum,
+            amount_out,
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            fee_amount,
+            max_deviation_bps,
+            total_value: composition.total_value,
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+    /// 取消限价单：投资者或下单者随时可以取消，订单过期后任何人都可以取消
+    pub fn cancel_limit_order(ctx: Context<CancelLimitOrder>) -> Result<()> {
+        msg!("开始取消限价单...");
+        msg!("用户地址: {}", ctx.accounts.user.key());
+        msg!("订单编号: {}", ctx.accounts.order.order_id);
+
+        let order = &ctx.accounts.order;
+        let user = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            user == order.placed_by
+                || ctx.accounts.vault.has_role(VaultRole::Investor, &user)
+                || now > order.expiry,
+            ErrorCode::Unauthorized
+        );
+
+        msg!("限价单已取消!");
+
+        emit!(LimitOrderCancelledEvent {
+            vault: ctx.accounts.vault.key(),
+            order_id: order.order_id,
+            cancelled_by: user,
+            timestamp_microseconds: now as u64 * 1_000_000, // 转换为微秒
+        });
+
+        Ok(())
+    }
+
+// ⚠️ Byreal CLMM 集成注意事项：
+// 1. 当前实现直接调用区块链上已部署的 Byreal CLMM 合约，不导入其代码库
+// 2. 使用正确的指令标识符和账户结构，确保与 Byreal CLMM 的 SwapSingle 结构匹配
+// 3. 需要验证的账户关系：
+//    - pool_state.amm_config == amm_config.key()
+//    - pool_state.observation_key == observation_state.key()
+//    - tick_array.pool_id == pool_state.key()
+//    - input_vault 和 output_vault 必须是池子的正确金库
+// 4. 指令标识符已从 Byreal CLMM IDL 中获取：
+//    - swap: [248, 198, 158, 145, 225, 117, 135, 200]
+//    - swap_v2: [43, 4, 237, 11, 26, 201, 30, 98]
+// 5. 池子地址计算需要包含 amm_config 参数
+// 6. 需要处理 remaining_accounts 中的 tick_array_bitmap_extension 和其他 tick arrays
+// 7. 实际部署时，确保使用正确的程序 ID（devnet/mainnet）
+}
+
+/// Byreal CLMM 交换函数
+fn byreal_swap_exact_input_single(
+    token_in: Pubkey,
+    token_out: Pubkey,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Result<u64> {
+    msg!("开始 Byreal CLMM 交换...");
+    msg!("输入代币: {}", token_in);
+    msg!("输出代币: {}", token_out);
+    msg!("输入金额: {}", amount_in);
+    msg!("最小输出金额: {}", amount_out_minimum);
+    
+    // Byreal CLMM 使用池子地址而不是交易对
+    // 需要先获取或计算池子地址
+    // 这里需要传入 amm_config，暂时使用默认值
+    let amm_config = Pubkey::default(); // 实际使用时需要传入正确的 amm_config
+    let pool_address = get_byreal_pool_address(amm_config, token_in, token_out)?;
+    msg!("Byreal 池子地址: {}", pool_address);
+    
+    // 执行 CLMM 交换
+    let amount_out = execute_byreal_clmm_swap(
+        pool_address,
+        token_in,
+        token_out,
+        amount_in,
+        amount_out_minimum,
+    )?;
+    
+    msg!("Byreal CLMM 交换完成，输出金额: {}", amount_out);
+    Ok(amount_out)
+}
+
+/// 获取 Byreal 池子地址
+fn get_byreal_pool_address(amm_config: Pubkey, token_a: Pubkey, token_b: Pubkey) -> Result<Pubkey> {
+    msg!("获取 Byreal 池子地址: {} <-> {}", token_a, token_b);
+    
+    // 在 Byreal CLMM 中，池子地址是通过确定性方式计算的
+    // 根据 Byreal 源码，池子地址的种子是 [b"pool", amm_config, token_mint_0, token_mint_1]
+    // 其中 token_mint_0 和 token_mint_1 按地址排序
+    
+    let (token_mint_0, token_mint_1) = if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    };
+    
+    let (pool_address, _bump) = Pubkey::find_program_address(
+        &[
+            b"pool",
+            amm_config.as_ref(),
+            token_mint_0.as_ref(),
+            token_mint_1.as_ref(),
+        ],
+        &Pubkey::from_str(BYREAL_CLMM_PROGRAM_ID).unwrap(),
+    );
+    
+    msg!("计算得到的池子地址: {}", pool_address);
+    Ok(pool_address)
+}
+
+/// 执行 Byreal CLMM 交换
+fn execute_byreal_clmm_swap(
+    pool_address: Pubkey,
+    token_in: Pubkey,
+    token_out: Pubkey,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Result<u64> {
+    msg!("执行 Byreal CLMM 交换...");
+    msg!("池子地址: {}", pool_address);
+    msg!("输入代币: {}", token_in);
+    msg!("输出代币: {}", token_out);
+    msg!("输入金额: {}", amount_in);
+    msg!("最小输出金额: {}", amount_out_minimum);
+    
+    // 构建 Byreal CLMM 交换指令
+          let swap_instruction = build_byreal_clmm_swap_instruction(
+          amount_in,
+          amount_out_minimum,
+          0, // sqrt_price_limit_x64: 0 表示无限制
+          true, // is_base_input: true 表示 base input 模式
+      )?;
+    
+    // 执行交换指令
+    // 直接返回模拟的输出金额
+    let amount_out = amount_in * 99 / 100; // 模拟 1% 滑点
+    
+    msg!("Byreal CLMM 交换执行完成，输出: {}", amount_out);
+    Ok(amount_out)
+}
+
+/// 构建 Byreal CLMM 交换指令
+fn build_byreal_clmm_swap_instruction(
+    amount: u64,
+    other_amount_threshold: u64,
+    sqrt_price_limit_x64: u128,
+    is_base_input: bool,
+) -> Result<Vec<u8>> {
+    msg!("构建 Byreal CLMM 交换指令...");
+    
+    // 根据 Byreal CLMM 的实际指令格式构建
+    // 参考 byreal-clmm/programs/amm/src/instructions/swap.rs 中的 swap 函数参数
+    
+    let mut instruction_data = Vec::new();
+    
+    // 指令标识符 - 使用正确的 discriminator
+    instruction_data.extend_from_slice(&BYREAL_SWAP_INSTRUCTION_DISCRIMINATOR);
+    
+    // 添加参数 - 根据 Byreal CLMM 的 swap 函数参数
+    instruction_data.extend_from_slice(&amount.to_le_bytes()); // amount: u64
+    instruction_data.extend_from_slice(&other_amount_threshold.to_le_bytes()); // other_amount_threshold: u64
+    instruction_data.extend_from_slice(&sqrt_price_limit_x64.to_le_bytes()); // sqrt_price_limit_x64: u128
+    instruction_data.push(if is_base_input { 1u8 } else { 0u8 }); // is_base_input: bool
+    
+    msg!("Byreal CLMM 交换指令构建完成");
+    Ok(instruction_data)
+}
+
+// 删除未使用的函数
+
+/// Byreal CLMM 单笔交换所需的账户集合
+pub struct ByrealSwapAccounts<'a, 'info> {
+    pub payer: &'a AccountInfo<'info>,
+    pub amm_config: &'a AccountInfo<'info>,
+    pub pool_state: &'a AccountInfo<'info>,
+    pub input_token_account: &'a AccountInfo<'info>,
+    pub output_token_account: &'a AccountInfo<'info>,
+    pub input_vault: &'a AccountInfo<'info>,
+    pub output_vault: &'a AccountInfo<'info>,
+    pub observation_state: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub tick_array: &'a AccountInfo<'info>,
+}
+
+/// 实际的 CPI 调用示例（需要在有完整账户上下文的地方使用）
+pub fn execute_byreal_swap_cpi<'info>(
+    ctx: &Context<SendTradeSignal<'info>>,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Result<u64> {
+    let payer = ctx.accounts.user.to_account_info();
+    let amm_config = ctx.accounts.amm_config.to_account_info();
+    let pool_state = ctx.accounts.pool_state.to_account_info();
+    let input_token_account = ctx.accounts.input_token_account.to_account_info();
+    let output_token_account = ctx.accounts.output_token_account.to_account_info();
+    let input_vault = ctx.accounts.input_vault.to_account_info();
+    let output_vault = ctx.accounts.output_vault.to_account_info();
+    let observation_state = ctx.accounts.observation_state.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    let tick_array = ctx.accounts.tick_array.to_account_info();
+
+    invoke_byreal_swap(
+        ByrealSwapAccounts {
+            payer: &payer,
+            amm_config: &amm_config,
+            pool_state: &pool_state,
+            input_token_account: &input_token_account,
+            output_token_account: &output_token_account,
+            input_vault: &input_vault,
+            output_vault: &output_vault,
+            observation_state: &observation_state,
+            token_program: &token_program,
+            tick_array: &tick_array,
+        },
+        amount_in,
+        amount_out_minimum,
+    )
+}
+
+/// 调用 Byreal CLMM 的 swap 指令
+fn invoke_byreal_swap(
+    accounts: ByrealSwapAccounts,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Result<u64> {
+    msg!("执行 Byreal CLMM CPI 调用...");
+    
+    let byreal_program_id = Pubkey::from_str(BYREAL_CLMM_PROGRAM_ID).unwrap();
+    
+    // 直接使用 invoke 调用，不使用 CPI 接口
+    
+    // 构建指令数据
+    let sqrt_price_limit_x64 = 0u128; // 0 表示无限制
+    let is_base_input = true; // 假设是 base input 模式
+    
+    let instruction_data = build_byreal_clmm_swap_instruction(
+        amount_in,
+        amount_out_minimum,
+        sqrt_price_limit_x64,
+        is_base_input,
+    )?;
+    
+    // 执行 CPI 调用
+    // 注意：这里我们直接调用指令，而不是使用 CPI 接口
+    // 因为我们是直接调用区块链上已部署的 Byreal CLMM 合约
+    
+    // 创建指令
+    let instruction = solana_program::instruction::Instruction {
+        program_id: byreal_program_id,
+        accounts: vec![
+            solana_program::instruction::AccountMeta::new(accounts.payer.key(), true),
+            solana_program::instruction::AccountMeta::new_readonly(accounts.amm_config.key(), false),
+            solana_program::instruction::AccountMeta::new(accounts.pool_state.key(), false),
+            solana_program::instruction::AccountMeta::new(accounts.input_token_account.key(), false),
+            solana_program::instruction::AccountMeta::new(accounts.output_token_account.key(), false),
+            solana_program::instruction::AccountMeta::new(accounts.input_vault.key(), false),
+            solana_program::instruction::AccountMeta::new(accounts.output_vault.key(), false),
+            solana_program::instruction::AccountMeta::new(accounts.observation_state.key(), false),
+            solana_program::instruction::AccountMeta::new_readonly(accounts.token_program.key(), false),
+            solana_program::instruction::AccountMeta::new(accounts.tick_array.key(), false),
+        ],
+        data: instruction_data,
+    };
+    
+    // 注意：在实际的 Anchor 程序中，我们通常使用 CPI 接口
+    // 但这里我们展示如何直接调用指令
+    // 实际实现中，你可能需要使用 solana_program::program::invoke 或类似方法
+    
+    // 使用 Anchor 的 CPI 调用
+    // 这里我们使用 solana_program::program::invoke 来调用 Byreal CLMM
+    let account_infos = vec![
+        accounts.payer.clone(),
+        accounts.amm_config.clone(),
+        accounts.pool_state.clone(),
+        accounts.input_token_account.clone(),
+        accounts.output_token_account.clone(),
+        accounts.input_vault.clone(),
+        accounts.output_vault.clone(),
+        accounts.observation_state.clone(),
+        accounts.token_program.clone(),
+        accounts.tick_array.clone(),
+    ];
+    
+    // 调用 Byreal CLMM 程序
+    invoke(
+        &instruction,
+        account_infos.as_slice(),
+    )?;
+    
+    // 注意：实际的输出金额需要从池子状态中读取
+    // 这里我们模拟一个合理的输出金额
+    let amount_out = amount_in * 99 / 100; // 模拟 1% 滑点
+    
+    msg!("Byreal CLMM 指令调用完成，输出: {}", amount_out);
+    Ok(amount_out)
+}
+
+/// 校验从 remaining_accounts 传入的一组 Byreal CLMM 交换账户
+/// 账户顺序: amm_config, pool_state, input_token_account, output_token_account,
+/// input_vault, output_vault, observation_state, tick_array
+/// 与 SendTradeSignal 中的账户约束保持一致
+fn validate_byreal_swap_leg<'info>(
+    leg: &'info [AccountInfo<'info>],
+    token_in: Pubkey,
+    token_out: Pubkey,
+) -> Result<()> {
+    let pool_state = AccountLoader::<PoolState>::try_from(&leg[1])?;
+    let (amm_config, observation_key) = {
+        let pool = pool_state.load()?;
+        (pool.amm_config, pool.observation_key)
+    };
+    require!(leg[0].key() == amm_config, ErrorCode::InvalidSwapAccounts);
+    require!(leg[6].key() == observation_key, ErrorCode::InvalidSwapAccounts);
+
+    let tick_array = AccountLoader::<TickArrayState>::try_from(&leg[7])?;
+    let tick_array_pool_id = tick_array.load()?.pool_id;
+    require!(tick_array_pool_id == leg[1].key(), ErrorCode::InvalidSwapAccounts);
+
+    let input_token_account = Account::<TokenAccount>::try_from(&leg[2])?;
+    let output_token_account = Account::<TokenAccount>::try_from(&leg[3])?;
+    require!(input_token_account.mint == token_in, ErrorCode::InvalidSwapAccounts);
+    require!(output_token_account.mint == token_out, ErrorCode::InvalidSwapAccounts);
+
+    Ok(())
+}
+
+/// 内部函数：使用一组 Byreal 交换账户为金库执行交换 (限价单、条件单等由 keeper 触发的交易共用)
+/// 扣除输入余额、把协议费转入 fee_recipient 并记入输出余额，返回 (输出金额, 费用金额)
+#[allow(clippy::too_many_arguments)]
+fn execute_vault_swap<'info>(
+    vault: &mut PersonalVault,
+    leg: &'info [AccountInfo<'info>],
+    payer: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    fee_recipient: &AccountInfo<'info>,
+    token_in: Pubkey,
+    token_out: Pubkey,
+    amount_in: u64,
+    amount_out_minimum: u64,
+    fee_rate: u64,
+) -> Result<(u64, u64)> {
+    require!(leg.len() == BYREAL_SWAP_LEG_ACCOUNTS, ErrorCode::InvalidSwapAccounts);
+    validate_byreal_swap_leg(leg, token_in, token_out)?;
+
+    let current_balance = get_token_balance(vault, token_in);
+    require!(current_balance >= amount_in, ErrorCode::InsufficientBalance);
+    set_token_balance(vault, token_in, current_balance - amount_in);
+
+    let amount_out = invoke_byreal_swap(
+        ByrealSwapAccounts {
+            payer,
+            amm_config: &leg[0],
+            pool_state: &leg[1],
+            input_token_account: &leg[2],
+            output_token_account: &leg[3],
+            input_vault: &leg[4],
+            output_vault: &leg[5],
+            observation_state: &leg[6],
+            token_program,
+            tick_array: &leg[7],
+        },
+        amount_in,
+        amount_out_minimum,
+    )?;
+    require!(amount_out >= amount_out_minimum, ErrorCode::InsufficientOutputAmount);
+
+    // 计算费用并转入平台金库
+    let (user_amount, fee_amount) = split_trade_fee(amount_out, fee_rate)?;
+
+    msg!("交换完成，输出金额: {}，费用金额: {}", amount_out, fee_amount);
+    transfer_trade_fee(token_program, &leg[3], fee_recipient, payer, fee_amount)?;
+
+    let current_out_balance = get_token_balance(vault, token_out);
+    let new_out_balance = current_out_balance
+        .checked_add(user_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    set_token_balance(vault, token_out, new_out_balance);
+
+    Ok((amount_out, fee_amount))
+}
+
+// 删除未使用的结构体
+
+/// 创建余额管理器上下文
+#[derive(Accounts)]
+pub struct CreateBalanceManager<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 32 + 32 + 1 + 4 + 40 * 10 + 1 + 56 + 1 + 2 + 32 + 32 + 4 + (32 + 1 + 56) * MAX_VAULT_BOTS + 8 + 9 + 1 + 32 + 8 + 8 * RECENT_SIGNAL_IDS + 1, // 账户标识符 + 各字段大小 + Vec长度 + 预留10个代币余额 + 策略开关 + 交易限制 + 预言机检查 + 待定管理员 + 运营方 + 附加机器人列表 + 时间锁延迟 + 金库费率 + 签名意图模式 + 最近信号 ID
+        seeds = [b"vault", user.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, PersonalVault>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// 初始化全局配置上下文
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 1 + 1 + 1 + 8 + 8 + 32 + (1 + 8 + 8 + 8), // 账户标识符 + 平台管理员 + 暂停标志 + 存款暂停标志 + bump + 最大费率 + 默认费率 + 平台金库 + 排队中的费率配置
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// 平台管理员，必须是程序的升级权限持有者，防止部署后被抢先初始化
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, crate::program::PersonalVault>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 设置费率配置上下文
+#[derive(Accounts)]
+pub struct SetFeeConfig<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 执行费率配置变更上下文
+#[derive(Accounts)]
+pub struct ExecuteFeeConfigChange<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub user: Signer<'info>,
+}
+
+/// 取消费率配置变更上下文
+#[derive(Accounts)]
+pub struct CancelFeeConfigChange<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 设置金库交易费率上下文
+#[derive(Accounts)]
+pub struct SetVaultFeeRate<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 更换平台金库上下文
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 设置协议暂停状态上下文
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 初始化预言机注册表上下文
+#[derive(Accounts)]
+pub struct InitializeOracleRegistry<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 8 + 2 + 1 + 4 + (32 + 1 + 32) * MAX_PRICE_FEEDS + 32 + 8 + 4 + 2 + 1, // 账户标识符 + 限制参数 + bump + Vec长度 + 价格源列表 + 喂价角色 + 最大价格过期时间 + 聚合参数
+        seeds = [b"oracle_registry"],
+        bump
+    )]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 设置预言机限制上下文
+#[derive(Accounts)]
+pub struct SetOracleRegistryLimits<'info> {
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 设置价格聚合参数上下文
+#[derive(Accounts)]
+pub struct SetPriceAggregation<'info> {
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 设置价格源上下文
+#[derive(Accounts)]
+pub struct SetPriceFeed<'info> {
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 移除价格源上下文
+#[derive(Accounts)]
+pub struct RemovePriceFeed<'info> {
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 设置管理员喂价配置上下文
+#[derive(Accounts)]
+pub struct SetPriceOracleConfig<'info> {
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub authority: Signer<'info>,
+}
+
+/// 更新价格上下文
+#[derive(Accounts)]
+#[instruction(token_a: Pubkey, token_b: Pubkey)]
+pub struct UpdatePrice<'info> {
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    #[account(
+        init_if_needed,
+        payer = oracle,
+        space = 8 + 32 + 32 + 16 + 8 + 1, // 账户标识符 + 代币 A + 代币 B + 价格 + 时间戳 + bump
+        seeds = [b"price", token_a.as_ref(), token_b.as_ref()],
+        bump
+    )]
+    pub pair_price: Account<'info, TokenPairPrice>,
+
+    #[account(
+        init_if_needed,
+        payer = oracle,
+        space = 8 + 32 + 32 + 16 + 8 + 1, // 账户标识符 + 代币 A + 代币 B + 价格 + 时间戳 + bump
+        seeds = [b"price", token_b.as_ref(), token_a.as_ref()],
+        bump
+    )]
+    pub reverse_pair_price: Account<'info, TokenPairPrice>,
+
+    #[account(mut)]
+    pub oracle: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 批量更新价格上下文
+#[derive(Accounts)]
+pub struct UpdatePricesBatch<'info> {
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    pub oracle: Signer<'info>,
+}
+
+/// 获取价格上下文
+#[derive(Accounts)]
+#[instruction(token_a: Pubkey, token_b: Pubkey)]
+pub struct GetPrice<'info> {
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    #[account(seeds = [b"price", token_a.as_ref(), token_b.as_ref()], bump = pair_price.bump)]
+    pub pair_price: Account<'info, TokenPairPrice>,
+}
+
+/// 计算代币价值上下文
+#[derive(Accounts)]
+pub struct GetTokenValueInAsset<'info> {
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+
+    #[account(
+        seeds = [b"price", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump = pair_price.bump
+    )]
+    pub pair_price: Account<'info, TokenPairPrice>,
+
+    pub mint_a: Account<'info, Mint>,
+
+    pub mint_b: Account<'info, Mint>,
+}
+
+/// 设置机器人地址上下文
+#[derive(Accounts)]
+pub struct SetBot<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+    
+    pub user: Signer<'info>,
+}
+
+/// 添加机器人上下文
+#[derive(Accounts)]
+pub struct AddBot<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 移除机器人上下文
+#[derive(Accounts)]
+pub struct RemoveBot<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 创建会话密钥上下文
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreateSession<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 4 + 4 + 1, // 账户标识符 + 金库 + 会话密钥 + 授权者 + 过期 slot + 过期时间戳 + 交易次数上限 + 已用次数 + bump
+        seeds = [b"session", vault.key().as_ref(), session_key.as_ref()],
+        bump
+    )]
+    pub session: Account<'info, BotSession>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 撤销会话密钥上下文
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"session", vault.key().as_ref(), session.session_key.as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, BotSession>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// 设置时间锁延迟上下文
+#[derive(Accounts)]
+pub struct SetTimelockDelay<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 提议配置变更上下文
+#[derive(Accounts)]
+#[instruction(kind: ConfigChangeKind)]
+pub struct QueueConfigChange<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    /// 全局配置账户 (FeeRate 由平台管理员提议，并按最大费率校验)
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 1 + 32 + (1 + 8 + 8 + 4) + (1 + 8) + 32 + 8 + 1, // 账户标识符 + 金库 + 变更类型 + 新地址 + 会话授权参数 + 新费率 + 提议者 + 可执行时间 + bump
+        seeds = [b"queued_change", vault.key().as_ref(), &[kind as u8]],
+        bump
+    )]
+    pub queued_change: Account<'info, QueuedConfigChange>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 执行配置变更上下文
+#[derive(Accounts)]
+pub struct ExecuteConfigChange<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"queued_change", vault.key().as_ref(), &[queued_change.kind as u8]],
+        bump = queued_change.bump
+    )]
+    pub queued_change: Account<'info, QueuedConfigChange>,
+
+    /// CHECK: 提议者，接收退还的租金
+    #[account(mut, address = queued_change.proposed_by)]
+    pub proposer: AccountInfo<'info>,
+
+    /// 会话账户 (仅 Session 类型，由执行者支付租金)
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 4 + 4 + 1, // 账户标识符 + 金库 + 会话密钥 + 授权者 + 过期 slot + 过期时间戳 + 交易次数上限 + 已用次数 + bump
+        seeds = [b"session", vault.key().as_ref(), queued_change.new_address.as_ref()],
+        bump
+    )]
+    pub session: Option<Account<'info, BotSession>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 取消配置变更上下文
+#[derive(Accounts)]
+pub struct CancelConfigChange<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"queued_change", vault.key().as_ref(), &[queued_change.kind as u8]],
+        bump = queued_change.bump
+    )]
+    pub queued_change: Account<'info, QueuedConfigChange>,
+
+    /// CHECK: 提议者，接收退还的租金
+    #[account(mut, address = queued_change.proposed_by)]
+    pub proposer: AccountInfo<'info>,
+
+    pub user: Signer<'info>,
+}
+
+/// 创建多签管理员上下文
+#[derive(Accounts)]
+pub struct CreateAdminMultisig<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 4 + 32 * MAX_MULTISIG_SIGNERS + 1 + 4 + 8 + 1, // 账户标识符 + 金库 + 签名者列表 + 阈值 + 签名者版本 + 提案数量 + bump
+        seeds = [b"admin_multisig", vault.key().as_ref()],
+        bump
+    )]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 创建多签提案上下文
+#[derive(Accounts)]
+pub struct CreateMultisigProposal<'info> {
+    #[account(mut, seeds = [b"admin_multisig", multisig.vault.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + (1 + 4 + 32 * MAX_MULTISIG_SIGNERS + 1) + 2 + 4 + 32 + 1, // 账户标识符 + 多签 + 编号 + 操作 (按最大的 SetSigners 计) + 审批位图 + 签名者版本 + 提议者 + bump
+        seeds = [b"multisig_proposal", multisig.key().as_ref(), &multisig.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 审批多签提案上下文
+#[derive(Accounts)]
+pub struct ApproveMultisigProposal<'info> {
+    #[account(seeds = [b"admin_multisig", multisig.vault.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    #[account(
+        mut,
+        has_one = multisig,
+        seeds = [b"multisig_proposal", multisig.key().as_ref(), &proposal.index.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    pub user: Signer<'info>,
+}
+
+/// 执行多签提案上下文
+#[derive(Accounts)]
+pub struct ExecuteMultisigProposal<'info> {
+    #[account(mut, address = multisig.vault)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(mut, seeds = [b"admin_multisig", multisig.vault.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    #[account(
+        mut,
+        has_one = multisig,
+        close = proposer,
+        seeds = [b"multisig_proposal", multisig.key().as_ref(), &proposal.index.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    /// CHECK: 提议者，接收退还的租金
+    #[account(mut, address = proposal.proposer)]
+    pub proposer: AccountInfo<'info>,
+
+    /// 排队的配置变更账户 (仅 QueueConfigChange 操作，由执行者支付租金)
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 1 + 32 + (1 + 8 + 8 + 4) + (1 + 8) + 32 + 8 + 1, // 账户标识符 + 金库 + 变更类型 + 新地址 + 会话授权参数 + 新费率 + 提议者 + 可执行时间 + bump
+        seeds = [
+            b"queued_change",
+            vault.key().as_ref(),
+            &[proposal.action.config_change_kind().ok_or(ErrorCode::InvalidMultisigAccounts)? as u8]
+        ],
+        bump
+    )]
+    pub queued_change: Option<Account<'info, QueuedConfigChange>>,
+
+    /// 会话账户 (仅 CreateSession 操作，由执行者支付租金)
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 4 + 4 + 1, // 账户标识符 + 金库 + 会话密钥 + 授权者 + 过期 slot + 过期时间戳 + 交易次数上限 + 已用次数 + bump
+        seeds = [
+            b"session",
+            vault.key().as_ref(),
+            proposal.action.session_key().ok_or(ErrorCode::InvalidMultisigAccounts)?.as_ref()
+        ],
+        bump
+    )]
+    pub session: Option<Account<'info, BotSession>>,
+
+    /// 要取消的排队配置变更 (仅 CancelConfigChange 操作，租金退还给变更的提议者)
+    #[account(
+        mut,
+        seeds = [b"queued_change", vault.key().as_ref(), &[cancelled_change.kind as u8]],
+        bump = cancelled_change.bump
+    )]
+    pub cancelled_change: Option<Account<'info, QueuedConfigChange>>,
+
+    /// CHECK: 被取消变更的提议者，接收退还的租金 (在指令中校验)
+    #[account(mut)]
+    pub change_proposer: Option<AccountInfo<'info>>,
+
+    /// 要撤销的会话账户 (仅 RevokeSession 操作，租金退还给执行者)
+    #[account(
+        mut,
+        seeds = [b"session", vault.key().as_ref(), revoked_session.session_key.as_ref()],
+        bump = revoked_session.bump
+    )]
+    pub revoked_session: Option<Account<'info, BotSession>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 取消多签提案上下文
+#[derive(Accounts)]
+pub struct CancelMultisigProposal<'info> {
+    #[account(seeds = [b"admin_multisig", multisig.vault.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    #[account(
+        mut,
+        has_one = multisig,
+        close = user,
+        seeds = [b"multisig_proposal", multisig.key().as_ref(), &proposal.index.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// 设置签名交易意图模式上下文
+#[derive(Accounts)]
+pub struct SetIntentMode<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 设置运营方上下文
+#[derive(Accounts)]
+pub struct SetOperator<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 提议新管理员上下文
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+    
+    pub user: Signer<'info>,
+}
+
+/// 接受管理员身份上下文
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+    
+    pub user: Signer<'info>,
+}
+
+/// 取消管理员转移上下文
+#[derive(Accounts)]
+pub struct CancelAdminTransfer<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+    
+    pub user: Signer<'info>,
+}
+
+/// 设置策略状态上下文
+#[derive(Accounts)]
+pub struct SetStrategyEnabled<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 设置交易限制上下文
+#[derive(Accounts)]
+pub struct SetTradeLimits<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 设置预言机检查上下文
+#[derive(Accounts)]
+pub struct SetOracleCheck<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    pub user: Signer<'info>,
+}
+
+/// 用户存款上下文
+#[derive(Accounts)]
+pub struct UserDeposit<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub mint: AccountInfo<'info>,
+}
+
+/// 用户取款上下文
+#[derive(Accounts)]
+pub struct UserWithdraw<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub mint: AccountInfo<'info>,
+}
+
+/// 获取余额上下文
+#[derive(Accounts)]
+pub struct GetBalance<'info> {
+    pub vault: Account<'info, PersonalVault>,
+}
+
+/// 查询投资组合上下文
+#[derive(Accounts)]
+pub struct GetPortfolioComposition<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    /// 预言机注册表账户
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+}
+
+/// 发送交易信号上下文
+#[derive(Accounts)]
+pub struct SendTradeSignal<'info> {
+    #[account(mut)]
+    pub vault: Box<Account<'info, PersonalVault>>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// 预言机注册表账户 (启用预言机检查时必须提供，价格账户通过 remaining_accounts 传入)
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Option<Box<Account<'info, OracleRegistry>>>,
+    
+    pub user: Signer<'info>,
+    
+    /// 会话账户 (调用者是会话密钥而不是机器人时必须提供)
+    #[account(
+        mut,
+        seeds = [b"session", vault.key().as_ref(), user.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Box<Account<'info, BotSession>>>,
+    
+    /// CHECK: 指令 sysvar (金库启用签名交易意图模式时必须提供)
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+    
+    /// 费用接收者账户，必须是平台金库在输出代币上的关联代币账户
+    #[account(
+        mut,
+        address = get_associated_token_address(&config.treasury, &output_token_account.mint)
+            @ ErrorCode::InvalidFeeRecipient
+    )]
+    pub fee_recipient: Box<Account<'info, TokenAccount>>,
+    
+    /// Byreal CLMM 相关账户 - 根据 SwapSingle 结构
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>, // AMM 配置账户
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>, // 池子状态账户
+    #[account(mut)]
+    pub input_token_account: Box<Account<'info, TokenAccount>>, // 用户输入代币账户
+    #[account(mut)]
+    pub output_token_account: Box<Account<'info, TokenAccount>>, // 用户输出代币账户
+    #[account(mut)]
+    pub input_vault: Box<Account<'info, TokenAccount>>, // 池子输入代币金库
+    #[account(mut)]
+    pub output_vault: Box<Account<'info, TokenAccount>>, // 池子输出代币金库
+    #[account(mut, address = pool_state.load()?.observation_key)]
+    pub observation_state: AccountLoader<'info, ObservationState>, // 观察状态账户
+    #[account(mut, constraint = tick_array.load()?.pool_id == pool_state.key())]
+    pub tick_array: AccountLoader<'info, TickArrayState>, // Tick 数组账户
+    pub token_program: Program<'info, Token>, // SPL Token 程序
+    pub system_program: Program<'info, System>, // System 程序
+    
+    // 删除有问题的字段
+}
+
+/// 紧急退出上下文
+#[derive(Accounts)]
+pub struct EmergencyExitAll<'info> {
+    #[account(mut)]
+    pub vault: Account<'info, PersonalVault>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// 预言机注册表账户 (平台管理员调用且金库启用预言机检查时必须提供)
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Option<Box<Account<'info, OracleRegistry>>>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>, // SPL Token 程序
+}
+
+/// 挂限价单上下文
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PlaceLimitOrder<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + 32 + 32 + 32 + 8 + 8 + 8 + 1, // 账户标识符 + 金库 + 订单编号 + 下单者 + 卖出代币 + 买入代币 + 卖出数量 + 最小买入数量 + 过期时间 + bump
+        seeds = [b"limit_order", vault.key().as_ref(), &order_id.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 执行限价单上下文
+#[derive(Accounts)]
+pub struct ExecuteLimitOrder<'info> {
+    #[account(mut, address = order.vault)]
+    pub vault: Box<Account<'info, PersonalVault>>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// 预言机注册表账户 (机器人订单且金库启用预言机检查时必须提供)
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Option<Box<Account<'info, OracleRegistry>>>,
+
+    #[account(
+        mut,
+        close = placed_by,
+        seeds = [b"limit_order", order.vault.as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    /// CHECK: 下单者，接收退还的租金
+    #[account(mut, address = order.placed_by)]
+    pub placed_by: AccountInfo<'info>,
+
+    /// 费用接收者账户，必须是平台金库在输出代币上的关联代币账户
+    #[account(
+        mut,
+        address = get_associated_token_address(&config.treasury, &order.token_out)
+            @ ErrorCode::InvalidFeeRecipient
+    )]
+    pub fee_recipient: Box<Account<'info, TokenAccount>>,
+
+    pub keeper: Signer<'info>,
+
+    pub token_program: Program<'info, Token>, // SPL Token 程序
+}
+
+/// 取消限价单上下文
+#[derive(Accounts)]
+pub struct CancelLimitOrder<'info> {
+    #[account(address = order.vault)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        mut,
+        close = placed_by,
+        seeds = [b"limit_order", order.vault.as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, LimitOrder>,
+
+    /// CHECK: 下单者，接收退还的租金
+    #[account(mut, address = order.placed_by)]
+    pub placed_by: AccountInfo<'info>,
+
+    pub user: Signer<'info>,
+}
+
+/// 创建定投计划上下文
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CreateDcaSchedule<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + 32 + 32 + 32 + 8 + 8 + 4 + 4 + 8 + 2 + 1, // 账户标识符 + 金库 + 计划编号 + 创建者 + 卖出代币 + 买入代币 + 每次数量 + 间隔 + 总次数 + 已执行次数 + 下次执行时间 + 最大滑点 + bump
+        seeds = [b"dca", vault.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump
+    )]
+    pub schedule: Account<'info, DcaSchedule>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 执行定投上下文
+#[derive(Accounts)]
+pub struct ExecuteDca<'info> {
+    #[account(mut, address = schedule.vault)]
+    pub vault: Box<Account<'info, PersonalVault>>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// 预言机注册表账户，价格账户通过 remaining_accounts 传入
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Box<Account<'info, OracleRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"dca", schedule.vault.as_ref(), &schedule.schedule_id.to_le_bytes()],
+        bump = schedule.bump
+    )]
+    pub schedule: Account<'info, DcaSchedule>,
+
+    /// CHECK: 计划创建者，计划完成后接收退还的租金
+    #[account(mut, address = schedule.created_by)]
+    pub created_by: AccountInfo<'info>,
+
+    /// 费用接收者账户，必须是平台金库在输出代币上的关联代币账户
+    #[account(
+        mut,
+        address = get_associated_token_address(&config.treasury, &schedule.token_out)
+            @ ErrorCode::InvalidFeeRecipient
+    )]
+    pub fee_recipient: Box<Account<'info, TokenAccount>>,
+
+    pub keeper: Signer<'info>,
+
+    pub token_program: Program<'info, Token>, // SPL Token 程序
+}
+
+/// 取消定投计划上下文
+#[derive(Accounts)]
+pub struct CancelDcaSchedule<'info> {
+    #[account(address = schedule.vault)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        mut,
+        close = created_by,
+        seeds = [b"dca", schedule.vault.as_ref(), &schedule.schedule_id.to_le_bytes()],
+        bump = schedule.bump
+    )]
+    pub schedule: Account<'info, DcaSchedule>,
+
+    /// CHECK: 计划创建者，接收退还的租金
+    #[account(mut, address = schedule.created_by)]
+    pub created_by: AccountInfo<'info>,
+
+    pub user: Signer<'info>,
+}
+
+/// 设置再平衡目标权重上下文
+#[derive(Accounts)]
+pub struct SetTargetWeights<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 32 + 2 + 2 + 4 + (32 + 2) * MAX_TARGET_WEIGHTS + 1, // 账户标识符 + 金库 + 基础资产 + 容忍带宽 + 最大滑点 + 目标权重列表 + bump
+        seeds = [b"rebalance_targets", vault.key().as_ref()],
+        bump
+    )]
+    pub rebalance_targets: Account<'info, RebalanceTargets>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 执行再平衡上下文
+#[derive(Accounts)]
+#[instruction(token_in: Pubkey, token_out: Pubkey)]
+pub struct RebalanceStep<'info> {
+    #[account(mut)]
+    pub vault: Box<Account<'info, PersonalVault>>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// 预言机注册表账户，价格账户通过 remaining_accounts 传入
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Box<Account<'info, OracleRegistry>>,
+
+    #[account(
+        seeds = [b"rebalance_targets", vault.key().as_ref()],
+        bump = rebalance_targets.bump
+    )]
+    pub rebalance_targets: Account<'info, RebalanceTargets>,
+
+    /// 费用接收者账户，必须是平台金库在输出代币上的关联代币账户
+    #[account(
+        mut,
+        address = get_associated_token_address(&config.treasury, &token_out)
+            @ ErrorCode::InvalidFeeRecipient
+    )]
+    pub fee_recipient: Box<Account<'info, TokenAccount>>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>, // SPL Token 程序
+}
+
+/// 挂 TWAP 订单上下文
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PlaceTwapOrder<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + 32 + 32 + 32 + 8 + 8 + 8 + 2 + 8 + 8 + 4 + 8 + 1, // 账户标识符 + 金库 + 订单编号 + 下单者 + 卖出代币 + 买入代币 + 总数量 + 每份数量 + 最小间隔 + 滑点 + 已卖出 + 累计买入 + 子交换数量 + 上次执行 slot + bump
+        seeds = [b"twap_order", vault.key().as_ref(), &order_id.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, TwapOrder>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 执行 TWAP 子交换上下文
+#[derive(Accounts)]
+pub struct ExecuteTwapSlice<'info> {
+    #[account(mut, address = order.vault)]
+    pub vault: Box<Account<'info, PersonalVault>>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// 预言机注册表账户，价格账户通过 remaining_accounts 传入
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Box<Account<'info, OracleRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"twap_order", order.vault.as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, TwapOrder>,
+
+    /// CHECK: 下单者，订单完成后接收退还的租金
+    #[account(mut, address = order.placed_by)]
+    pub placed_by: AccountInfo<'info>,
+
+    /// 费用接收者账户，必须是平台金库在输出代币上的关联代币账户
+    #[account(
+        mut,
+        address = get_associated_token_address(&config.treasury, &order.token_out)
+            @ ErrorCode::InvalidFeeRecipient
+    )]
+    pub fee_recipient: Box<Account<'info, TokenAccount>>,
+
+    pub keeper: Signer<'info>,
+
+    pub token_program: Program<'info, Token>, // SPL Token 程序
+}
+
+/// 取消 TWAP 订单上下文
+#[derive(Accounts)]
+pub struct CancelTwapOrder<'info> {
+    #[account(address = order.vault)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        mut,
+        close = placed_by,
+        seeds = [b"twap_order", order.vault.as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, TwapOrder>,
+
+    /// CHECK: 下单者，接收退还的租金
+    #[account(mut, address = order.placed_by)]
+    pub placed_by: AccountInfo<'info>,
+
+    pub user: Signer<'info>,
+}
+
+/// 挂条件单上下文
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PlaceConditionalOrder<'info> {
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + 32 + 1 + 32 + 32 + 8 + 16 + 2 + 16 + 8 + 2 + 1, // 账户标识符 + 金库 + 订单编号 + 下单者 + 类型 + 卖出代币 + 买入代币 + 卖出数量 + 触发价 + 回落比例 + 最高价 + 过期时间 + 最大滑点 + bump
+        seeds = [b"conditional_order", vault.key().as_ref(), &order_id.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, ConditionalOrder>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 触发条件单上下文
+#[derive(Accounts)]
+pub struct CrankConditionalOrder<'info> {
+    #[account(mut, address = order.vault)]
+    pub vault: Box<Account<'info, PersonalVault>>,
+
+    /// 全局配置账户
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    /// 预言机注册表账户，价格账户通过 remaining_accounts 传入
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Box<Account<'info, OracleRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"conditional_order", order.vault.as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, ConditionalOrder>,
+
+    /// CHECK: 下单者，成交后接收退还的租金
+    #[account(mut, address = order.placed_by)]
+    pub placed_by: AccountInfo<'info>,
+
+    /// 费用接收者账户，必须是平台金库在输出代币上的关联代币账户
+    #[account(
+        mut,
+        address = get_associated_token_address(&config.treasury, &order.token_out)
+            @ ErrorCode::InvalidFeeRecipient
+    )]
+    pub fee_recipient: Box<Account<'info, TokenAccount>>,
+
+    pub keeper: Signer<'info>,
+
+    pub token_program: Program<'info, Token>, // SPL Token 程序
+}
+
+/// 取消条件单上下文
+#[derive(Accounts)]
+pub struct CancelConditionalOrder<'info> {
+    #[account(address = order.vault)]
+    pub vault: Account<'info, PersonalVault>,
+
+    #[account(
+        mut,
+        close = placed_by,
+        seeds = [b"conditional_order", order.vault.as_ref(), &order.order_id.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, ConditionalOrder>,
+
+    /// CHECK: 下单者，接收退还的租金
+    #[account(mut, address = order.placed_by)]
+    pub placed_by: AccountInfo<'info>,
+
+    pub user: Signer<'info>,
+}
+
+/// 事件定义
+// 余额管理器创建事件 (对应 Aptos 的 BalanceManagerCreatedEvent)
+#[event]
+pub struct BalanceManagerCreatedEvent {
+    pub user: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 用户存款事件 (对应 Aptos 的 UserDepositEvent)
+#[event]
+pub struct UserDepositEvent {
+    pub user: Pubkey,
+    pub asset_metadata: Pubkey, // 对应 Aptos 的 Object<Metadata>
+    pub amount: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// 用户取款事件 (对应 Aptos 的 UserWithdrawEvent)
+#[event]
+pub struct UserWithdrawEvent {
+    pub user: Pubkey,
+    pub asset_metadata: Pubkey, // 对应 Aptos 的 Object<Metadata>
+    pub amount: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// 交易信号事件 (对应 Aptos 的 TradeSignalEvent)
+#[event]
+pub struct TradeSignalEvent {
+    pub user: Pubkey,
+    pub from_asset_metadata: Pubkey, // 对应 Aptos 的 Object<Metadata>
+    pub to_asset_metadata: Pubkey,   // 对应 Aptos 的 Object<Metadata>
+    pub amount_in: u64,
+    pub amount_out_min: u64,
+    pub amount_out: u64,
+    pub fee_recipient: Pubkey,
+    pub fee_amount: u64,
+    pub signal_id: u64,
+    pub schedule_id: u64, // 定投计划编号，普通交易信号为 0
+    pub timestamp_microseconds: u64,
+}
+
+// 策略状态变化事件 (对应 UniswapVault 的 StrategyStatusChanged)
+#[event]
+pub struct StrategyStatusChangedEvent {
+    pub enabled: bool,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 费率配置更新事件
+#[event]
+pub struct FeeConfigUpdatedEvent {
+    pub max_fee_rate: u64,
+    pub default_fee_rate: u64,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 费率提高排队事件
+#[event]
+pub struct FeeConfigQueuedEvent {
+    pub max_fee_rate: u64,
+    pub default_fee_rate: u64,
+    pub proposed_by: Pubkey,
+    pub eta: i64,
+    pub timestamp_microseconds: u64,
+}
+
+// 费率提高取消事件
+#[event]
+pub struct FeeConfigChangeCancelledEvent {
+    pub max_fee_rate: u64,
+    pub default_fee_rate: u64,
+    pub cancelled_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 金库交易费率更新事件
+#[event]
+pub struct VaultFeeRateUpdatedEvent {
+    pub vault: Pubkey,
+    pub old_fee_rate: Option<u64>,
+    pub new_fee_rate: Option<u64>,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 平台金库更换事件
+#[event]
+pub struct TreasuryUpdatedEvent {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 协议暂停状态变化事件
+#[event]
+pub struct ProtocolPauseChangedEvent {
+    pub paused: bool,
+    pub deposits_paused: bool,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 紧急退出事件
+#[event]
+pub struct EmergencyExitEvent {
+    pub user: Pubkey,
+    pub base_asset_metadata: Pubkey,
+    pub positions_exited: u32,
+    pub total_amount_out: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// 限价单创建事件
+#[event]
+pub struct LimitOrderPlacedEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub placed_by: Pubkey,
+    pub token_in: Pubkey,
+    pub token_out: Pubkey,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub expiry: i64,
+    pub timestamp_microseconds: u64,
+}
+
+// 限价单成交事件
+#[event]
+pub struct LimitOrderExecutedEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub keeper: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_recipient: Pubkey,
+    pub fee_amount: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// 限价单取消事件
+#[event]
+pub struct LimitOrderCancelledEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub cancelled_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 定投计划创建事件
+#[event]
+pub struct DcaScheduleCreatedEvent {
+    pub vault: Pubkey,
+    pub schedule_id: u64,
+    pub token_in: Pubkey,
+    pub token_out: Pubkey,
+    pub amount_per_execution: u64,
+    pub interval_seconds: i64,
+    pub total_executions: u32,
+    pub start_at: i64,
+    pub max_slippage_bps: u16,
+    pub timestamp_microseconds: u64,
+}
+
+// 定投计划取消事件
+#[event]
+pub struct DcaScheduleCancelledEvent {
+    pub vault: Pubkey,
+    pub schedule_id: u64,
+    pub executions_done: u32,
+    pub cancelled_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 再平衡目标权重更新事件
+#[event]
+pub struct TargetWeightsUpdatedEvent {
+    pub vault: Pubkey,
+    pub base_mint: Pubkey,
+    pub tolerance_bps: u16,
+    pub max_slippage_bps: u16,
+    pub targets: Vec<TargetWeight>,
+    pub timestamp_microseconds: u64,
+}
+
+// 再平衡交易事件
+#[event]
+pub struct RebalanceStepEvent {
+    pub vault: Pubkey,
+    pub executed_by: Pubkey,
+    pub token_in: Pubkey,
+    pub token_out: Pubkey,
+    pub amount_in: u64,
+    pub amount_out_minimum: u64,
+    pub amount_out: u64,
+    pub fee_recipient: Pubkey,
+    pub fee_amount: u64,
+    pub max_deviation_bps: u64,
+    pub total_value: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// TWAP 订单创建事件
+#[event]
+pub struct TwapOrderPlacedEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub placed_by: Pubkey,
+    pub token_in: Pubkey,
+    pub token_out: Pubkey,
+    pub total_amount_in: u64,
+    pub slice_amount: u64,
+    pub min_interval_slots: u64,
+    pub max_slippage_bps: u16,
+    pub timestamp_microseconds: u64,
+}
+
+// TWAP 子交换执行事件
+#[event]
+pub struct TwapSliceExecutedEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub slice_index: u32,
+    pub keeper: Pubkey,
+    pub amount_in: u64,
+    pub amount_out_minimum: u64,
+    pub amount_out: u64,
+    pub fee_recipient: Pubkey,
+    pub fee_amount: u64,
+    pub amount_filled: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// TWAP 订单取消事件
+#[event]
+pub struct TwapOrderCancelledEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub amount_filled: u64,
+    pub cancelled_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 条件单创建事件
+#[event]
+pub struct ConditionalOrderPlacedEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub placed_by: Pubkey,
+    pub kind: ConditionalOrderKind,
+    pub token_in: Pubkey,
+    pub token_out: Pubkey,
+    pub amount_in: u64,
+    pub trigger_price: u128,
+    pub trailing_bps: u16,
+    pub expiry: i64,
+    pub max_slippage_bps: u16,
+    pub timestamp_microseconds: u64,
+}
+
+// 追踪止损最高价更新事件
+#[event]
+pub struct ConditionalOrderPeakUpdatedEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub peak_price: u128,
+    pub timestamp_microseconds: u64,
+}
+
+// 条件单成交事件
+#[event]
+pub struct ConditionalOrderExecutedEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub kind: ConditionalOrderKind,
+    pub keeper: Pubkey,
+    pub trigger_price: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_recipient: Pubkey,
+    pub fee_amount: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// 条件单取消事件
+#[event]
+pub struct ConditionalOrderCancelledEvent {
+    pub vault: Pubkey,
+    pub order_id: u64,
+    pub cancelled_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 交易限制更新事件
+#[event]
+pub struct TradeLimitsUpdatedEvent {
+    pub user: Pubkey,
+    pub max_trades_per_window: u32,
+    pub trade_window_slots: u64,
+    pub max_notional_per_trade: u64,
+    pub max_daily_notional: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// 预言机检查设置事件
+#[event]
+pub struct OracleCheckUpdatedEvent {
+    pub user: Pubkey,
+    pub enabled: bool,
+    pub max_slippage_bps: u16,
+    pub timestamp_microseconds: u64,
+}
+
+// 预言机限制更新事件
+#[event]
+pub struct OracleRegistryLimitsUpdatedEvent {
+    pub max_staleness_seconds: u64,
+    pub max_confidence_bps: u16,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 价格聚合参数更新事件
+#[event]
+pub struct PriceAggregationUpdatedEvent {
+    pub min_price_sources: u8,
+    pub max_deviation_bps: u16,
+    pub twap_window_seconds: u32,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 管理员喂价配置更新事件
+#[event]
+pub struct PriceOracleConfigUpdatedEvent {
+    pub oracle_authority: Pubkey,
+    pub max_price_age: u64,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 价格源更新事件
+#[event]
+pub struct PriceFeedUpdatedEvent {
+    pub mint: Pubkey,
+    pub source: PriceSource,
+    pub feed_id: [u8; 32],
+    pub removed: bool,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 价格更新事件 (对应 PriceOracle 的 PriceUpdated)
+#[event]
+pub struct PriceUpdatedEvent {
+    pub token_a: Pubkey,
+    pub token_b: Pubkey,
+    pub price: u128,
+    pub timestamp_microseconds: u64,
+}
+
+// 机器人添加/更新事件
+#[event]
+pub struct BotAddedEvent {
+    pub user: Pubkey,
+    pub bot: Pubkey,
+    pub permissions: u8,
+    pub max_trades_per_window: u32,
+    pub trade_window_slots: u64,
+    pub max_notional_per_trade: u64,
+    pub max_daily_notional: u64,
+    pub timestamp_microseconds: u64,
+}
+
+// 机器人移除事件
+#[event]
+pub struct BotRemovedEvent {
+    pub user: Pubkey,
+    pub bot: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 会话密钥创建事件
+#[event]
+pub struct SessionCreatedEvent {
+    pub vault: Pubkey,
+    pub session_key: Pubkey,
+    pub granted_by: Pubkey,
+    pub expires_slot: u64,
+    pub expires_at: i64,
+    pub max_trades: u32,
+    pub timestamp_microseconds: u64,
 }
 
-// 删除未使用的函数
+// 会话密钥撤销事件
+#[event]
+pub struct SessionRevokedEvent {
+    pub vault: Pubkey,
+    pub session_key: Pubkey,
+    pub revoked_by: Pubkey,
+    pub timestamp_microseconds: u64,
+}
 
-/// 实际的 CPI 调用示例（需要在有完整账户上下文的地方使用）
-pub fn execute_byreal_swap_cpi<'info>(
-    ctx: &Context<SendTradeSignal<'info>>,
-    amount_in: u64,
-    amount_out_minimum: u64,
-) -> Result<u64> {
-    msg!("执行 Byreal CLMM CPI 调用...");
-    
-    let byreal_program_id = Pubkey::from_str(BYREAL_CLMM_PROGRAM_ID).unwrap();
-    
-    // 直接使用 invoke 调用，不使用 CPI 接口
-    
-    // 构建指令数据
-    let sqrt_price_limit_x64 = 0u128; // 0 表示无限制
-    let is_base_input = true; // 假设是 base input 模式
-    
-    let instruction_data = build_byreal_clmm_swap_instruction(
-        amount_in,
-        amount_out_minimum,
-        sqrt_price_limit_x64,
-        is_base_input,
-    )?;
-    
-    // 执行 CPI 调用
-    // 注意：这里我们直接调用指令，而不是使用 CPI 接口
-    // 因为我们是直接调用区块链上已部署的 Byreal CLMM 合约
-    
-    // 创建指令
-    let instruction = solana_program::instruction::Instruction {
-        program_id: byreal_program_id,
-        accounts: vec![
-            solana_program::instruction::AccountMeta::new(ctx.accounts.user.key(), true),
-            solana_program::instruction::AccountMeta::new_readonly(ctx.accounts.amm_config.key(), false),
-            solana_program::instruction::AccountMeta::new(ctx.accounts.pool_state.key(), false),
-            solana_program::instruction::AccountMeta::new(ctx.accounts.input_token_account.key(), false),
-            solana_program::instruction::AccountMeta::new(ctx.accounts.output_token_account.key(), false),
-            solana_program::instruction::AccountMeta::new(ctx.accounts.input_vault.key(), false),
-            solana_program::instruction::AccountMeta::new(ctx.accounts.output_vault.key(), false),
-            solana_program::instruction::AccountMeta::new(ctx.accounts.observation_state.key(), false),
-            solana_program::instruction::AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
-            solana_program::instruction::AccountMeta::new(ctx.accounts.tick_array.key(), false),
-        ],
-        data: instruction_data,
-    };
-    
-    // 注意：在实际的 Anchor 程序中，我们通常使用 CPI 接口
-    // 但这里我们展示如何直接调用指令
-    // 实际实现中，你可能需要使用 solana_program::program::invoke 或类似方法
-    
-    // 使用 Anchor 的 CPI 调用
-    // 这里我们使用 solana_program::program::invoke 来调用 Byreal CLMM
-    let accounts = vec![
-        ctx.accounts.user.to_account_info(),
-        ctx.accounts.amm_config.to_account_info(),
-        ctx.accounts.pool_state.to_account_info(),
-        ctx.accounts.input_token_account.to_account_info(),
-        ctx.accounts.output_token_account.to_account_info(),
-        ctx.accounts.input_vault.to_account_info(),
-        ctx.accounts.output_vault.to_account_info(),
-        ctx.accounts.observation_state.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.tick_array.to_account_info(),
-    ];
-    
-    // 调用 Byreal CLMM 程序
-    invoke(
-        &instruction,
-        accounts.as_slice(),
-    )?;
-    
-    // 注意：实际的输出金额需要从池子状态中读取
-    // 这里我们模拟一个合理的输出金额
-    let amount_out = amount_in * 99 / 100; // 模拟 1% 滑点
-    
-    msg!("Byreal CLMM 指令调用完成，输出: {}", amount_out);
-    Ok(amount_out)
+// 时间锁延迟更新事件
+#[event]
+pub struct TimelockDelayUpdatedEvent {
+    pub vault: Pubkey,
+    pub old_timelock_seconds: i64,
+    pub new_timelock_seconds: i64,
+    pub changed_by: Pubkey,
+    pub timestamp_microseconds: u64,
 }
 
-// 删除未使用的结构体
+// 配置变更排队事件
+#[event]
+pub struct ConfigChangeQueuedEvent {
+    pub vault: Pubkey,
+    pub kind: ConfigChangeKind,
+    pub new_address: Pubkey,
+    pub new_fee_rate: Option<u64>,
+    pub proposed_by: Pubkey,
+    pub eta: i64,
+    pub timestamp_microseconds: u64,
+}
 
-/// 创建余额管理器上下文
-#[derive(Accounts)]
-pub struct CreateBalanceManager<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 32 + 32 + 32 + 1 + 4 + 40 * 10, // 账户标识符 + 各字段大小 + Vec长度 + 预留10个代币余额
-        seeds = [b"vault", user.key().as_ref()],
-        bump
-    )]
-    pub vault: Account<'info, PersonalVault>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+// 配置变更执行事件
+#[event]
+pub struct ConfigChangeExecutedEvent {
+    pub vault: Pubkey,
+    pub kind: ConfigChangeKind,
+    pub new_address: Pubkey,
+    pub executed_by: Pubkey,
+    pub timestamp_microseconds: u64,
 }
 
-/// 设置机器人地址上下文
-#[derive(Accounts)]
-pub struct SetBot<'info> {
-    #[account(mut)]
-    pub vault: Account<'info, PersonalVault>,
-    
-    pub user: Signer<'info>,
+// 配置变更取消事件
+#[event]
+pub struct ConfigChangeCancelledEvent {
+    pub vault: Pubkey,
+    pub kind: ConfigChangeKind,
+    pub cancelled_by: Pubkey,
+    pub timestamp_microseconds: u64,
 }
 
-/// 设置管理员上下文
-#[derive(Accounts)]
-pub struct SetAdmin<'info> {
-    #[account(mut)]
-    pub vault: Account<'info, PersonalVault>,
-    
-    pub user: Signer<'info>,
+// 多签管理员创建事件
+#[event]
+pub struct AdminMultisigCreatedEvent {
+    pub vault: Pubkey,
+    pub multisig: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub created_by: Pubkey,
+    pub timestamp_microseconds: u64,
 }
 
-/// 用户存款上下文
-#[derive(Accounts)]
-pub struct UserDeposit<'info> {
-    #[account(mut)]
-    pub vault: Account<'info, PersonalVault>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub mint: AccountInfo<'info>,
+// 多签提案创建事件
+#[event]
+pub struct MultisigProposalCreatedEvent {
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub proposer: Pubkey,
+    pub timestamp_microseconds: u64,
 }
 
-/// 用户取款上下文
-#[derive(Accounts)]
-pub struct UserWithdraw<'info> {
-    #[account(mut)]
-    pub vault: Account<'info, PersonalVault>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub mint: AccountInfo<'info>,
+// 多签提案审批事件
+#[event]
+pub struct MultisigProposalApprovedEvent {
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub approver: Pubkey,
+    pub approvals: u8,
+    pub timestamp_microseconds: u64,
 }
 
-/// 获取余额上下文
-#[derive(Accounts)]
-pub struct GetBalance<'info> {
-    pub vault: Account<'info, PersonalVault>,
+// 多签提案执行事件
+#[event]
+pub struct MultisigProposalExecutedEvent {
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub executed_by: Pubkey,
+    pub timestamp_microseconds: u64,
 }
 
-/// 发送交易信号上下文
-#[derive(Accounts)]
-pub struct SendTradeSignal<'info> {
-    #[account(mut)]
-    pub vault: Account<'info, PersonalVault>,
-    
-    pub user: Signer<'info>,
-    
-    /// 费用接收者账户
-    pub fee_recipient: AccountInfo<'info>,
-    
-    /// Byreal CLMM 相关账户 - 根据 SwapSingle 结构
-    #[account(address = pool_state.load()?.amm_config)]
-    pub amm_config: Box<Account<'info, AmmConfig>>, // AMM 配置账户
-    #[account(mut)]
-    pub pool_state: AccountLoader<'info, PoolState>, // 池子状态账户
-    #[account(mut)]
-    pub input_token_account: Box<Account<'info, TokenAccount>>, // 用户输入代币账户
-    #[account(mut)]
-    pub output_token_account: Box<Account<'info, TokenAccount>>, // 用户输出代币账户
-    #[account(mut)]
-    pub input_vault: Box<Account<'info, TokenAccount>>, // 池子输入代币金库
-    #[account(mut)]
-    pub output_vault: Box<Account<'info, TokenAccount>>, // 池子输出代币金库
-    #[account(mut, address = pool_state.load()?.observation_key)]
-    pub observation_state: AccountLoader<'info, ObservationState>, // 观察状态账户
-    #[account(mut, constraint = tick_array.load()?.pool_id == pool_state.key())]
-    pub tick_array: AccountLoader<'info, TickArrayState>, // Tick 数组账户
-    pub token_program: Program<'info, Token>, // SPL Token 程序
-    pub system_program: Program<'info, System>, // System 程序
-    
-    // 删除有问题的字段
+// 多签提案取消事件
+#[event]
+pub struct MultisigProposalCancelledEvent {
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub cancelled_by: Pubkey,
+    pub timestamp_microseconds: u64,
 }
 
-/// 事件定义
-// 余额管理器创建事件 (对应 Aptos 的 BalanceManagerCreatedEvent)
+// 签名交易意图模式更新事件
 #[event]
-pub struct BalanceManagerCreatedEvent {
-    pub user: Pubkey,
+pub struct IntentModeUpdatedEvent {
+    pub vault: Pubkey,
+    pub require_signed_intents: bool,
+    pub intent_signer: Pubkey,
+    pub changed_by: Pubkey,
     pub timestamp_microseconds: u64,
 }
 
-// 用户存款事件 (对应 Aptos 的 UserDepositEvent)
+// 运营方更新事件
 #[event]
-pub struct UserDepositEvent {
-    pub user: Pubkey,
-    pub asset_metadata: Pubkey, // 对应 Aptos 的 Object<Metadata>
-    pub amount: u64,
+pub struct OperatorUpdatedEvent {
+    pub old_operator: Pubkey,
+    pub new_operator: Pubkey,
+    pub changed_by: Pubkey,
     pub timestamp_microseconds: u64,
 }
 
-// 用户取款事件 (对应 Aptos 的 UserWithdrawEvent)
+// 管理员提议事件
 #[event]
-pub struct UserWithdrawEvent {
-    pub user: Pubkey,
-    pub asset_metadata: Pubkey, // 对应 Aptos 的 Object<Metadata>
-    pub amount: u64,
+pub struct AdminProposedEvent {
+    pub current_admin: Pubkey,
+    pub pending_admin: Pubkey,
     pub timestamp_microseconds: u64,
 }
 
-// 交易信号事件 (对应 Aptos 的 TradeSignalEvent)
+// 管理员转移完成事件
 #[event]
-pub struct TradeSignalEvent {
-    pub user: Pubkey,
-    pub from_asset_metadata: Pubkey, // 对应 Aptos 的 Object<Metadata>
-    pub to_asset_metadata: Pubkey,   // 对应 Aptos 的 Object<Metadata>
-    pub amount_in: u64,
-    pub amount_out_min: u64,
-    pub amount_out: u64,
-    pub fee_recipient: Pubkey,
-    pub fee_amount: u64,
+pub struct AdminTransferredEvent {
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub timestamp_microseconds: u64,
+}
+
+// 管理员转移取消事件
+#[event]
+pub struct AdminTransferCancelledEvent {
+    pub admin: Pubkey,
+    pub cancelled_admin: Pubkey,
     pub timestamp_microseconds: u64,
 }
 
@@ -820,4 +6661,576 @@ pub enum ErrorCode {
     InvalidFeeRate,
     #[msg("输出金额不足")]
     InsufficientOutputAmount,
-} 
\ No newline at end of file
+    #[msg("策略已禁用")]
+    StrategyDisabled,
+    #[msg("协议已暂停")]
+    ProtocolPaused,
+    #[msg("存款已暂停")]
+    DepositsPaused,
+    #[msg("无效的基础资产地址")]
+    InvalidBaseMint,
+    #[msg("紧急退出的最小输出或账户数量不匹配")]
+    InvalidExitLegs,
+    #[msg("无效的交换账户")]
+    InvalidSwapAccounts,
+    #[msg("数值溢出")]
+    MathOverflow,
+    #[msg("无效的交易限制参数")]
+    InvalidTradeLimits,
+    #[msg("超过时间窗口内的最大交易次数")]
+    TradeRateLimitExceeded,
+    #[msg("超过单笔交易最大名义金额")]
+    TradeNotionalExceeded,
+    #[msg("超过每日最大名义金额")]
+    DailyNotionalExceeded,
+    #[msg("无效的滑点基点")]
+    InvalidSlippageBps,
+    #[msg("无法获取预言机价格")]
+    OraclePriceUnavailable,
+    #[msg("最小输出低于预言机价格允许范围")]
+    MinimumOutputBelowOracle,
+    #[msg("无效的价格账户")]
+    InvalidPriceAccount,
+    #[msg("价格未经过完整验证")]
+    PriceNotFullyVerified,
+    #[msg("价格源 ID 不匹配")]
+    PriceFeedMismatch,
+    #[msg("无效的预言机价格")]
+    InvalidOraclePrice,
+    #[msg("预言机价格已过期")]
+    StalePrice,
+    #[msg("预言机价格置信区间过宽")]
+    PriceConfidenceTooWide,
+    #[msg("代币未配置价格源")]
+    PriceFeedNotConfigured,
+    #[msg("价格源数量超过上限")]
+    TooManyPriceFeeds,
+    #[msg("批量参数长度不匹配或为空")]
+    InvalidBatchLength,
+    #[msg("价格源之间偏离过大")]
+    PriceSourcesDisagree,
+    #[msg("无效的价格聚合参数")]
+    InvalidAggregationConfig,
+    #[msg("缺少代币 (Mint) 账户")]
+    MintAccountMissing,
+    #[msg("没有待接受的管理员")]
+    NoPendingAdmin,
+    #[msg("只有待定管理员可以接受")]
+    OnlyPendingAdmin,
+    #[msg("无效的平台金库地址")]
+    InvalidTreasury,
+    #[msg("费用接收者必须是平台金库的关联代币账户")]
+    InvalidFeeRecipient,
+    #[msg("附加机器人数量已达上限")]
+    TooManyBots,
+    #[msg("无效的机器人权限位掩码")]
+    InvalidBotPermissions,
+    #[msg("机器人不存在")]
+    BotNotFound,
+    #[msg("机器人没有执行该操作的权限")]
+    BotPermissionDenied,
+    #[msg("无效的会话过期设置")]
+    InvalidSessionExpiry,
+    #[msg("会话已过期")]
+    SessionExpired,
+    #[msg("会话交易次数已用完")]
+    SessionTradeLimitReached,
+    #[msg("无效的时间锁延迟")]
+    InvalidTimelockDelay,
+    #[msg("已启用时间锁，请通过排队变更修改配置")]
+    TimelockEnabled,
+    #[msg("时间锁尚未到期")]
+    TimelockNotExpired,
+    #[msg("无效的多签签名者集合")]
+    InvalidMultisigSigners,
+    #[msg("无效的多签审批阈值")]
+    InvalidMultisigThreshold,
+    #[msg("不是多签签名者")]
+    NotMultisigSigner,
+    #[msg("已经审批过该提案")]
+    AlreadyApproved,
+    #[msg("提案审批数量未达到阈值")]
+    MultisigThresholdNotMet,
+    #[msg("签名者集合已变更，提案已失效")]
+    StaleMultisigProposal,
+    #[msg("缺少投资者签名的交易意图")]
+    MissingSignedIntent,
+    #[msg("无效的交易意图签名指令")]
+    InvalidIntentSignature,
+    #[msg("交易意图与交易信号参数不一致")]
+    IntentMismatch,
+    #[msg("交易意图已过期")]
+    IntentExpired,
+    #[msg("交易意图随机数已使用")]
+    IntentNonceUsed,
+    #[msg("无效的交易信号 ID")]
+    InvalidSignalId,
+    #[msg("交易信号 ID 已执行过")]
+    DuplicateSignalId,
+    #[msg("交易信号已过期")]
+    SignalExpired,
+    #[msg("无效的订单过期时间")]
+    InvalidOrderExpiry,
+    #[msg("订单已过期")]
+    OrderExpired,
+    #[msg("池子现价未达到限价")]
+    LimitPriceNotReached,
+    #[msg("无效的池子价格")]
+    InvalidPoolPrice,
+    #[msg("无效的触发价格设置")]
+    InvalidTriggerPrice,
+    #[msg("条件单尚未触发")]
+    TriggerNotReached,
+    #[msg("无效的定投计划参数")]
+    InvalidDcaSchedule,
+    #[msg("定投尚未到执行时间")]
+    DcaNotDue,
+    #[msg("无效的 TWAP 订单参数")]
+    InvalidTwapOrder,
+    #[msg("TWAP 子交换尚未到最小间隔")]
+    TwapSliceNotDue,
+    #[msg("无效的目标权重")]
+    InvalidTargetWeights,
+    #[msg("投资组合权重在容忍带宽内，无需再平衡")]
+    PortfolioWithinTolerance,
+    #[msg("卖出代币必须高于目标权重，买入代币必须低于目标权重")]
+    InvalidRebalancePair,
+    #[msg("缺少已配置价格源的账户")]
+    PriceAccountMissing,
+    #[msg("会话账户与配置变更类型不匹配")]
+    InvalidSessionAccount,
+    #[msg("多签提案账户与提案操作不匹配")]
+    InvalidMultisigAccounts,
+    #[msg("没有排队中的费率配置")]
+    NoPendingFeeConfig,
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trade_limits_enforce_per_trade_and_window_caps() {
+        let mut limits = TradeLimits {
+            max_trades_per_window: 2,
+            trade_window_slots: 100,
+            max_notional_per_trade: 1_000,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_and_record_trade_limits(&mut limits, 1_001, 210, 0).unwrap_err(),
+            ErrorCode::TradeNotionalExceeded.into()
+        );
+        check_and_record_trade_limits(&mut limits, 1_000, 110, 0).unwrap();
+        check_and_record_trade_limits(&mut limits, 1_000, 150, 0).unwrap();
+        assert_eq!(
+            check_and_record_trade_limits(&mut limits, 1, 209, 0).unwrap_err(),
+            ErrorCode::TradeRateLimitExceeded.into()
+        );
+
+        // 窗口从 slot 110 开始，slot 210 时重置
+        check_and_record_trade_limits(&mut limits, 1, 210, 0).unwrap();
+        assert_eq!(limits.window_start_slot, 210);
+        assert_eq!(limits.trades_in_window, 1);
+    }
+
+    #[test]
+    fn trade_limits_roll_over_daily_notional() {
+        let mut limits = TradeLimits {
+            max_daily_notional: 1_500,
+            ..Default::default()
+        };
+        let start = 10 * SECONDS_PER_DAY;
+
+        check_and_record_trade_limits(&mut limits, 1_000, 0, start).unwrap();
+        assert_eq!(
+            check_and_record_trade_limits(&mut limits, 501, 0, start + SECONDS_PER_DAY - 1).unwrap_err(),
+            ErrorCode::DailyNotionalExceeded.into()
+        );
+        check_and_record_trade_limits(&mut limits, 500, 0, start + SECONDS_PER_DAY - 1).unwrap();
+
+        // 满一天后重新累计
+        check_and_record_trade_limits(&mut limits, 1_500, 0, start + SECONDS_PER_DAY).unwrap();
+        assert_eq!(limits.day_start_timestamp, start + SECONDS_PER_DAY);
+        assert_eq!(limits.daily_notional, 1_500);
+    }
+
+    fn session(expires_slot: u64, expires_at: i64, max_trades: u32) -> BotSession {
+        BotSession {
+            vault: Pubkey::new_unique(),
+            session_key: Pubkey::new_unique(),
+            granted_by: Pubkey::new_unique(),
+            expires_slot,
+            expires_at,
+            max_trades,
+            trades_used: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn bot_trades_count_against_vault_and_bot_limits() {
+        let bot = Pubkey::new_unique();
+        let new_vault = || PersonalVault {
+            trade_limits: TradeLimits {
+                max_daily_notional: 1_000,
+                ..Default::default()
+            },
+            bots: vec![BotEntry {
+                key: bot,
+                permissions: BOT_PERMISSION_SWAP,
+                limits: TradeLimits {
+                    max_notional_per_trade: 400,
+                    ..Default::default()
+                },
+            }],
+            ..Default::default()
+        };
+
+        // 附加机器人自身的单笔限制
+        assert_eq!(
+            check_and_record_bot_trade(&mut new_vault(), bot, 401, 0, 0).unwrap_err(),
+            ErrorCode::TradeNotionalExceeded.into()
+        );
+
+        let mut vault = new_vault();
+        check_and_record_bot_trade(&mut vault, bot, 400, 0, 0).unwrap();
+        check_and_record_bot_trade(&mut vault, bot, 400, 0, 0).unwrap();
+        assert_eq!(vault.trade_limits.daily_notional, 800);
+
+        // 主机器人只受金库限制，两者共用金库的每日名义金额
+        check_and_record_bot_trade(&mut vault, Pubkey::new_unique(), 200, 0, 0).unwrap();
+        assert_eq!(
+            check_and_record_bot_trade(&mut vault, bot, 1, 0, 0).unwrap_err(),
+            ErrorCode::DailyNotionalExceeded.into()
+        );
+    }
+
+    #[test]
+    fn order_slippage_rejects_zero_and_full_range() {
+        // 0 时触发后永远无法成交
+        assert_eq!(
+            validate_order_slippage_bps(0).unwrap_err(),
+            ErrorCode::InvalidSlippageBps.into()
+        );
+        // 10000 时最小输出为 0
+        assert_eq!(
+            validate_order_slippage_bps(oracle::BPS_DENOMINATOR as u16).unwrap_err(),
+            ErrorCode::InvalidSlippageBps.into()
+        );
+        validate_order_slippage_bps(1).unwrap();
+        validate_order_slippage_bps(oracle::BPS_DENOMINATOR as u16 - 1).unwrap();
+    }
+
+    #[test]
+    fn twap_order_rejects_zero_interval_and_zero_slippage() {
+        validate_twap_order(1_000, 100, 10, 50).unwrap();
+        // 同一 slot 内执行所有子交换
+        assert_eq!(
+            validate_twap_order(1_000, 100, 0, 50).unwrap_err(),
+            ErrorCode::InvalidTwapOrder.into()
+        );
+        // 子交换的预言机最小输出无法满足
+        assert_eq!(
+            validate_twap_order(1_000, 100, 10, 0).unwrap_err(),
+            ErrorCode::InvalidSlippageBps.into()
+        );
+        assert_eq!(
+            validate_twap_order(1_000, 1_001, 10, 50).unwrap_err(),
+            ErrorCode::InvalidTwapOrder.into()
+        );
+        assert_eq!(
+            validate_twap_order(1_000, 0, 10, 50).unwrap_err(),
+            ErrorCode::InvalidTwapOrder.into()
+        );
+    }
+
+    #[test]
+    fn trade_fee_split_credits_vault_with_remainder() {
+        // 费率 3000 / 1_000_000 = 0.3%
+        assert_eq!(split_trade_fee(1_000_000, 3_000).unwrap(), (997_000, 3_000));
+        // 向下取整，零头留给金库
+        assert_eq!(split_trade_fee(333, 3_000).unwrap(), (333, 0));
+        assert_eq!(split_trade_fee(u64::MAX, 0).unwrap(), (u64::MAX, 0));
+        assert_eq!(
+            split_trade_fee(u64::MAX, FEE_RATE_DENOMINATOR).unwrap(),
+            (0, u64::MAX)
+        );
+        assert_eq!(
+            split_trade_fee(FEE_RATE_DENOMINATOR, FEE_RATE_DENOMINATOR + 1).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn vault_fee_rate_increases_require_queue_under_timelock() {
+        let config = Config {
+            authority: Pubkey::new_unique(),
+            paused: false,
+            deposits_paused: false,
+            bump: 0,
+            max_fee_rate: 10_000,
+            default_fee_rate: 3_000,
+            treasury: Pubkey::new_unique(),
+            pending_fee_config: None,
+        };
+        let mut vault = PersonalVault::default();
+
+        // 没有时间锁时可以直接提高
+        check_vault_fee_rate_change(&vault, &config, Some(5_000)).unwrap();
+        assert_eq!(
+            check_vault_fee_rate_change(&vault, &config, Some(10_001)).unwrap_err(),
+            ErrorCode::InvalidFeeRate.into()
+        );
+
+        vault.timelock_seconds = 3_600;
+        assert_eq!(
+            check_vault_fee_rate_change(&vault, &config, Some(3_001)).unwrap_err(),
+            ErrorCode::TimelockEnabled.into()
+        );
+        check_vault_fee_rate_change(&vault, &config, Some(1_000)).unwrap();
+        check_vault_fee_rate_change(&vault, &config, None).unwrap();
+
+        // 已有更低的金库费率时，恢复默认费率也是提高
+        vault.fee_rate_override = Some(1_000);
+        assert_eq!(
+            check_vault_fee_rate_change(&vault, &config, None).unwrap_err(),
+            ErrorCode::TimelockEnabled.into()
+        );
+    }
+
+    #[test]
+    fn bot_session_expires_by_slot_and_timestamp() {
+        let mut by_slot = session(100, 0, 0);
+        consume_bot_session(&mut by_slot, 99, i64::MAX).unwrap();
+        assert_eq!(
+            consume_bot_session(&mut by_slot, 100, 0).unwrap_err(),
+            ErrorCode::SessionExpired.into()
+        );
+
+        let mut by_time = session(0, 1_000, 0);
+        consume_bot_session(&mut by_time, u64::MAX, 999).unwrap();
+        assert_eq!(
+            consume_bot_session(&mut by_time, 0, 1_000).unwrap_err(),
+            ErrorCode::SessionExpired.into()
+        );
+    }
+
+    #[test]
+    fn bot_session_enforces_trade_cap() {
+        let mut capped = session(0, 0, 2);
+        consume_bot_session(&mut capped, 1, 1).unwrap();
+        consume_bot_session(&mut capped, 2, 2).unwrap();
+        assert_eq!(
+            consume_bot_session(&mut capped, 3, 3).unwrap_err(),
+            ErrorCode::SessionTradeLimitReached.into()
+        );
+        assert_eq!(capped.trades_used, 2);
+    }
+
+    #[test]
+    fn multisig_signers_reject_invalid_sets() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let c = Pubkey::new_unique();
+
+        validate_multisig_signers(&[a, b, c], 2).unwrap();
+        validate_multisig_signers(&[a, b, c], 3).unwrap();
+
+        assert_eq!(
+            validate_multisig_signers(&[a, b, a], 2).unwrap_err(),
+            ErrorCode::InvalidMultisigSigners.into()
+        );
+        assert_eq!(
+            validate_multisig_signers(&[a, Pubkey::default()], 1).unwrap_err(),
+            ErrorCode::InvalidMultisigSigners.into()
+        );
+        assert_eq!(
+            validate_multisig_signers(&[], 1).unwrap_err(),
+            ErrorCode::InvalidMultisigSigners.into()
+        );
+        let too_many: Vec<Pubkey> = (0..=MAX_MULTISIG_SIGNERS).map(|_| Pubkey::new_unique()).collect();
+        assert_eq!(
+            validate_multisig_signers(&too_many, 1).unwrap_err(),
+            ErrorCode::InvalidMultisigSigners.into()
+        );
+
+        // 阈值必须在 1..=N 之间
+        assert_eq!(
+            validate_multisig_signers(&[a, b, c], 4).unwrap_err(),
+            ErrorCode::InvalidMultisigThreshold.into()
+        );
+        assert_eq!(
+            validate_multisig_signers(&[a, b, c], 0).unwrap_err(),
+            ErrorCode::InvalidMultisigThreshold.into()
+        );
+    }
+
+    fn multisig_proposal(multisig: &AdminMultisig, proposer: usize, action: MultisigAction) -> MultisigProposal {
+        MultisigProposal {
+            multisig: Pubkey::new_unique(),
+            index: 0,
+            action,
+            approvals: 1 << proposer,
+            signers_version: multisig.signers_version,
+            proposer: multisig.signers[proposer],
+            bump: 0,
+        }
+    }
+
+    fn queued_change(kind: ConfigChangeKind) -> QueuedConfigChange {
+        QueuedConfigChange {
+            vault: Pubkey::new_unique(),
+            kind,
+            new_address: Pubkey::new_unique(),
+            session_grant: None,
+            new_fee_rate: None,
+            proposed_by: Pubkey::new_unique(),
+            eta: 0,
+            bump: 0,
+        }
+    }
+
+    fn bot_session(session_key: Pubkey) -> BotSession {
+        BotSession {
+            vault: Pubkey::new_unique(),
+            session_key,
+            granted_by: Pubkey::new_unique(),
+            expires_slot: 100,
+            expires_at: 0,
+            max_trades: 0,
+            trades_used: 0,
+            bump: 0,
+        }
+    }
+
+    fn two_of_three_multisig() -> AdminMultisig {
+        AdminMultisig {
+            vault: Pubkey::new_unique(),
+            signers: vec![Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()],
+            threshold: 2,
+            signers_version: 0,
+            proposal_count: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn multisig_cancels_queued_config_change_after_approval() {
+        let multisig = two_of_three_multisig();
+        let mut proposal = multisig_proposal(
+            &multisig,
+            0,
+            MultisigAction::CancelConfigChange { kind: ConfigChangeKind::Bot },
+        );
+        let change = queued_change(ConfigChangeKind::Bot);
+
+        // 只有提议者审批时未达到阈值
+        assert_eq!(
+            check_multisig_executable(&multisig, &proposal).unwrap_err(),
+            ErrorCode::MultisigThresholdNotMet.into()
+        );
+        assert_eq!(
+            record_multisig_approval(&multisig, &mut proposal, &multisig.signers[0]).unwrap_err(),
+            ErrorCode::AlreadyApproved.into()
+        );
+        assert_eq!(
+            record_multisig_approval(&multisig, &mut proposal, &Pubkey::new_unique()).unwrap_err(),
+            ErrorCode::NotMultisigSigner.into()
+        );
+        assert_eq!(record_multisig_approval(&multisig, &mut proposal, &multisig.signers[2]).unwrap(), 2);
+        check_multisig_executable(&multisig, &proposal).unwrap();
+
+        // 必须传入同类型的排队变更，且不能传入会话账户
+        proposal.action.check_target_accounts(Some(&change), None).unwrap();
+        assert_eq!(
+            proposal
+                .action
+                .check_target_accounts(Some(&queued_change(ConfigChangeKind::Admin)), None)
+                .unwrap_err(),
+            ErrorCode::InvalidMultisigAccounts.into()
+        );
+        assert_eq!(
+            proposal.action.check_target_accounts(None, None).unwrap_err(),
+            ErrorCode::InvalidMultisigAccounts.into()
+        );
+        assert_eq!(
+            proposal
+                .action
+                .check_target_accounts(Some(&change), Some(&bot_session(Pubkey::new_unique())))
+                .unwrap_err(),
+            ErrorCode::InvalidMultisigAccounts.into()
+        );
+    }
+
+    #[test]
+    fn multisig_revokes_session_after_approval() {
+        let mut multisig = two_of_three_multisig();
+        let session_key = Pubkey::new_unique();
+        let mut proposal = multisig_proposal(&multisig, 1, MultisigAction::RevokeSession { session_key });
+
+        record_multisig_approval(&multisig, &mut proposal, &multisig.signers[0]).unwrap();
+        check_multisig_executable(&multisig, &proposal).unwrap();
+
+        proposal.action.check_target_accounts(None, Some(&bot_session(session_key))).unwrap();
+        assert_eq!(
+            proposal
+                .action
+                .check_target_accounts(None, Some(&bot_session(Pubkey::new_unique())))
+                .unwrap_err(),
+            ErrorCode::InvalidMultisigAccounts.into()
+        );
+        assert_eq!(
+            proposal
+                .action
+                .check_target_accounts(Some(&queued_change(ConfigChangeKind::Bot)), None)
+                .unwrap_err(),
+            ErrorCode::InvalidMultisigAccounts.into()
+        );
+
+        // 其他操作不能顺带关闭会话账户
+        let other = MultisigAction::SetStrategyEnabled { enabled: false };
+        other.check_target_accounts(None, None).unwrap();
+        assert_eq!(
+            other.check_target_accounts(None, Some(&bot_session(session_key))).unwrap_err(),
+            ErrorCode::InvalidMultisigAccounts.into()
+        );
+
+        // 更换签名者后旧提案失效
+        multisig.signers_version += 1;
+        assert_eq!(
+            check_multisig_executable(&multisig, &proposal).unwrap_err(),
+            ErrorCode::StaleMultisigProposal.into()
+        );
+    }
+
+    #[test]
+    fn signal_ids_reject_zero_and_replays_until_evicted() {
+        let mut vault = PersonalVault::default();
+
+        assert_eq!(
+            record_signal_id(&mut vault, 0).unwrap_err(),
+            ErrorCode::InvalidSignalId.into()
+        );
+
+        for signal_id in 1..=RECENT_SIGNAL_IDS as u64 {
+            record_signal_id(&mut vault, signal_id).unwrap();
+        }
+        assert_eq!(vault.signal_id_cursor, 0);
+        assert_eq!(
+            record_signal_id(&mut vault, 1).unwrap_err(),
+            ErrorCode::DuplicateSignalId.into()
+        );
+
+        // 第 33 个 ID 覆盖最早的 ID 1，之后 ID 1 可以再次使用 (覆盖 ID 2)，ID 3 仍被拒绝
+        record_signal_id(&mut vault, 33).unwrap();
+        assert_eq!(vault.recent_signal_ids[0], 33);
+        assert_eq!(vault.signal_id_cursor, 1);
+        record_signal_id(&mut vault, 1).unwrap();
+        assert_eq!(
+            record_signal_id(&mut vault, 3).unwrap_err(),
+            ErrorCode::DuplicateSignalId.into()
+        );
+    }
+}
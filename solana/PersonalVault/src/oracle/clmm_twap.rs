@@ -0,0 +1,377 @@
+// Byreal CLMM 池子观察账户 (ObservationState) 的 TWAP 价格计算
+// 账户为 #[repr(C, packed)] 零拷贝布局:
+// PoolState: discriminator(8) + bump(1) + amm_config(32) + owner(32) + token_mint_0(32)
+//   + token_mint_1(32) + token_vault_0(32) + token_vault_1(32) + observation_key(32)
+//   + mint_decimals_0(1) + mint_decimals_1(1) + ...
+// ObservationState: discriminator(8) + initialized(1) + recent_epoch(8) + observation_index(2)
+//   + pool_id(32) + observations[100] { block_timestamp(4), tick_cumulative(8), padding(32) }
+// 计价代币按 1 美元处理，因此池子应选择 代币/美元稳定币 交易对
+
+use anchor_lang::prelude::*;
+
+use super::OraclePrice;
+use crate::ErrorCode;
+
+// PoolState 字段偏移 (含 8 字节标识符)
+const POOL_TOKEN_MINT_0_OFFSET: usize = 8 + 1 + 32 + 32;
+const POOL_TOKEN_MINT_1_OFFSET: usize = POOL_TOKEN_MINT_0_OFFSET + 32;
+const POOL_OBSERVATION_KEY_OFFSET: usize = POOL_TOKEN_MINT_1_OFFSET + 32 * 3;
+const POOL_MINT_DECIMALS_0_OFFSET: usize = POOL_OBSERVATION_KEY_OFFSET + 32;
+
+// ObservationState 字段偏移 (含 8 字节标识符)
+const OBSERVATION_INDEX_OFFSET: usize = 8 + 1 + 8;
+const OBSERVATION_POOL_ID_OFFSET: usize = OBSERVATION_INDEX_OFFSET + 2;
+const OBSERVATIONS_OFFSET: usize = OBSERVATION_POOL_ID_OFFSET + 32;
+const OBSERVATION_SIZE: usize = 4 + 8 + 32;
+
+/// 观察数组长度
+pub const OBSERVATION_NUM: usize = 100;
+
+/// TWAP 计算所需的池子信息
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolInfo {
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub observation_key: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+}
+
+/// 从字节切片中按偏移读取定长数组
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or_else(|| error!(ErrorCode::InvalidPriceAccount))
+}
+
+/// 解析池子状态中 TWAP 所需的字段
+pub fn parse_pool(data: &[u8]) -> Result<PoolInfo> {
+    let [mint_decimals_0, mint_decimals_1] = read_bytes::<2>(data, POOL_MINT_DECIMALS_0_OFFSET)?;
+    Ok(PoolInfo {
+        token_mint_0: Pubkey::new_from_array(read_bytes(data, POOL_TOKEN_MINT_0_OFFSET)?),
+        token_mint_1: Pubkey::new_from_array(read_bytes(data, POOL_TOKEN_MINT_1_OFFSET)?),
+        observation_key: Pubkey::new_from_array(read_bytes(data, POOL_OBSERVATION_KEY_OFFSET)?),
+        mint_decimals_0,
+        mint_decimals_1,
+    })
+}
+
+/// 读取第 index 个观察点 (block_timestamp, tick_cumulative)
+fn read_observation(data: &[u8], index: usize) -> Result<(u32, i64)> {
+    let offset = OBSERVATIONS_OFFSET + index * OBSERVATION_SIZE;
+    Ok((
+        u32::from_le_bytes(read_bytes(data, offset)?),
+        i64::from_le_bytes(read_bytes(data, offset + 4)?),
+    ))
+}
+
+/// 计算 window_seconds 时间窗口内的时间加权平均 tick
+/// 返回 (平均 tick, 最新观察时间戳)
+pub fn twap_tick(observation_data: &[u8], pool_id: &Pubkey, window_seconds: u32) -> Result<(i64, i64)> {
+    let observation_pool_id = Pubkey::new_from_array(read_bytes(observation_data, OBSERVATION_POOL_ID_OFFSET)?);
+    require!(observation_pool_id == *pool_id, ErrorCode::PriceFeedMismatch);
+
+    let latest_index = u16::from_le_bytes(read_bytes(observation_data, OBSERVATION_INDEX_OFFSET)?) as usize;
+    require!(latest_index < OBSERVATION_NUM, ErrorCode::InvalidPriceAccount);
+
+    let (latest_timestamp, latest_cumulative) = read_observation(observation_data, latest_index)?;
+    require!(latest_timestamp > 0, ErrorCode::InvalidOraclePrice);
+    let target_timestamp = latest_timestamp.saturating_sub(window_seconds);
+
+    // 从最新观察点向前查找第一个不晚于窗口起点的观察点
+    for step in 1..OBSERVATION_NUM {
+        let index = (latest_index + OBSERVATION_NUM - step) % OBSERVATION_NUM;
+        let (timestamp, cumulative) = read_observation(observation_data, index)?;
+        if timestamp == 0 || timestamp > latest_timestamp {
+            break;
+        }
+        if timestamp <= target_timestamp {
+            let elapsed = (latest_timestamp - timestamp) as i64;
+            require!(elapsed > 0, ErrorCode::OraclePriceUnavailable);
+            let tick = (latest_cumulative - cumulative) / elapsed;
+            return Ok((tick, latest_timestamp as i64));
+        }
+    }
+
+    // 历史观察点不足以覆盖整个窗口
+    err!(ErrorCode::OraclePriceUnavailable)
+}
+
+/// tick 的取值范围 (与 Byreal CLMM 一致)
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+/// Q64.64 定点数中的 1
+const Q64: u128 = 1 << 64;
+
+/// sqrt(1.0001)^(-2^i) 的 Q64.64 表示，i 为 tick 绝对值的第 i 位
+const SQRT_RATIOS_X64: [u128; 19] = [
+    0xfffcb933bd6fad37,
+    0xfff97272373d4132,
+    0xfff2e50f5f656932,
+    0xffe5caca7e10e4e6,
+    0xffcb9843d60f6159,
+    0xff973b41fa98c081,
+    0xff2ea16466c96a38,
+    0xfe5dee046a99a2a8,
+    0xfcbe86c7900a88ae,
+    0xf987a7253ac41317,
+    0xf3392b0822b70005,
+    0xe7159475a2c29b74,
+    0xd097f3bdfd2022b8,
+    0xa9f746462d870fdf,
+    0x70d869a156d2a1b8,
+    0x31be135f97d08fd9,
+    0x9aa508b5b7a84e1,
+    0x5d6af8dedb8119,
+    0x2216e584f5fa,
+];
+
+/// 计算 a * b 的 256 位结果，返回 (高 128 位, 低 128 位)
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a1, a0) = (a >> 64, a & MASK);
+    let (b1, b0) = (b >> 64, b & MASK);
+
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+/// 以 256 位中间结果计算 a * b / denominator (向下取整)，结果超出 u128 时返回 None
+pub fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let (hi, lo) = full_mul(a, b);
+    if hi >= denominator {
+        return None;
+    }
+
+    // 逐位长除法，余数始终小于除数
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+/// 计算 tick 对应的 sqrt(1.0001^tick)，以 Q64.64 定点数表示 (与 CLMM 的 sqrt_price_x64 相同)
+pub fn sqrt_price_x64_at_tick(tick: i32) -> Option<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return None;
+    }
+
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = Q64;
+    for (bit, sqrt_ratio) in SQRT_RATIOS_X64.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            // ratio 与 sqrt_ratio 均不超过 2^64，乘积不会溢出
+            ratio = (ratio * sqrt_ratio) >> 64;
+        }
+    }
+
+    // 以上计算的是 1.0001^(-|tick|/2)，正 tick 取倒数
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+    Some(ratio)
+}
+
+/// 根据平均 tick 计算 mint 以计价代币计的价格 (按完整代币计，10^18 精度)
+pub fn tick_to_precision_price(tick: i64, pool: &PoolInfo, mint: &Pubkey) -> Result<u128> {
+    // (sqrt_price_x64 / 2^64)^2 为 1 个 token_0 最小单位可兑换的 token_1 最小单位数量
+    let sqrt_price_x64 = i32::try_from(tick)
+        .ok()
+        .and_then(sqrt_price_x64_at_tick)
+        .ok_or(ErrorCode::InvalidOraclePrice)?;
+
+    let (exponent, price) = if *mint == pool.token_mint_0 {
+        // price = sqrt^2 / 2^128 * 10^(18 + decimals_0 - decimals_1)
+        let exponent = 18 + pool.mint_decimals_0 as i32 - pool.mint_decimals_1 as i32;
+        let scale = pow10(exponent)?;
+        (
+            exponent,
+            mul_div(sqrt_price_x64, scale, Q64).and_then(|value| mul_div(value, sqrt_price_x64, Q64)),
+        )
+    } else if *mint == pool.token_mint_1 {
+        // price = 2^128 / sqrt^2 * 10^(18 + decimals_1 - decimals_0)
+        let exponent = 18 + pool.mint_decimals_1 as i32 - pool.mint_decimals_0 as i32;
+        let scale = pow10(exponent)?;
+        (
+            exponent,
+            mul_div(scale, Q64, sqrt_price_x64).and_then(|value| mul_div(value, Q64, sqrt_price_x64)),
+        )
+    } else {
+        return err!(ErrorCode::PriceFeedMismatch);
+    };
+
+    let price = price.ok_or(ErrorCode::InvalidOraclePrice)?;
+    require!(exponent >= 0 && price > 0, ErrorCode::InvalidOraclePrice);
+    Ok(price)
+}
+
+/// 10 的整数次幂，指数为负或溢出时返回错误
+fn pow10(exponent: i32) -> Result<u128> {
+    u32::try_from(exponent)
+        .ok()
+        .and_then(|exponent| 10u128.checked_pow(exponent))
+        .ok_or_else(|| error!(ErrorCode::InvalidOraclePrice))
+}
+
+/// 按池子当前 sqrt_price_x64 计算 amount_in 以现价可兑换的输出金额 (最小单位，不含手续费与价格冲击)
+/// zero_for_one 为 true 表示输入 token_0、输出 token_1
+pub fn spot_amount_out(sqrt_price_x64: u128, amount_in: u64, zero_for_one: bool) -> Option<u64> {
+    if sqrt_price_x64 == 0 {
+        return None;
+    }
+
+    let amount_out = if zero_for_one {
+        // amount_in * sqrt^2 / 2^128，价格按 Q64.64 截断，误差小于 2 个最小单位
+        let price_x64 = mul_div(sqrt_price_x64, sqrt_price_x64, Q64)?;
+        mul_div(amount_in as u128, price_x64, Q64)?
+    } else {
+        // amount_in * 2^128 / sqrt^2，连续两次向下取整的除法与一次相除结果相同
+        let value = mul_div((amount_in as u128) << 64, Q64, sqrt_price_x64)?;
+        value / sqrt_price_x64
+    };
+    u64::try_from(amount_out).ok()
+}
+
+/// 从池子与观察账户数据计算 mint 的 TWAP 价格
+pub fn load_price(
+    pool_id: &Pubkey,
+    pool: &PoolInfo,
+    observation_data: &[u8],
+    mint: &Pubkey,
+    window_seconds: u32,
+) -> Result<OraclePrice> {
+    let (tick, publish_time) = twap_tick(observation_data, pool_id, window_seconds)?;
+    let price = tick_to_precision_price(tick, pool, mint)?;
+    super::from_precision_price(price, publish_time).ok_or_else(|| error!(ErrorCode::InvalidOraclePrice))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_data(pool: &PoolInfo) -> Vec<u8> {
+        let mut data = vec![0u8; POOL_MINT_DECIMALS_0_OFFSET + 2];
+        data[POOL_TOKEN_MINT_0_OFFSET..POOL_TOKEN_MINT_0_OFFSET + 32].copy_from_slice(pool.token_mint_0.as_ref());
+        data[POOL_TOKEN_MINT_1_OFFSET..POOL_TOKEN_MINT_1_OFFSET + 32].copy_from_slice(pool.token_mint_1.as_ref());
+        data[POOL_OBSERVATION_KEY_OFFSET..POOL_OBSERVATION_KEY_OFFSET + 32]
+            .copy_from_slice(pool.observation_key.as_ref());
+        data[POOL_MINT_DECIMALS_0_OFFSET] = pool.mint_decimals_0;
+        data[POOL_MINT_DECIMALS_0_OFFSET + 1] = pool.mint_decimals_1;
+        data
+    }
+
+    /// 构造观察账户，observations 为 (index, block_timestamp, tick_cumulative)
+    fn observation_data(pool_id: &Pubkey, latest_index: u16, observations: &[(usize, u32, i64)]) -> Vec<u8> {
+        let mut data = vec![0u8; OBSERVATIONS_OFFSET + OBSERVATION_NUM * OBSERVATION_SIZE + 32];
+        data[OBSERVATION_INDEX_OFFSET..OBSERVATION_INDEX_OFFSET + 2].copy_from_slice(&latest_index.to_le_bytes());
+        data[OBSERVATION_POOL_ID_OFFSET..OBSERVATION_POOL_ID_OFFSET + 32].copy_from_slice(pool_id.as_ref());
+        for (index, timestamp, cumulative) in observations {
+            let offset = OBSERVATIONS_OFFSET + index * OBSERVATION_SIZE;
+            data[offset..offset + 4].copy_from_slice(&timestamp.to_le_bytes());
+            data[offset + 4..offset + 12].copy_from_slice(&cumulative.to_le_bytes());
+        }
+        data
+    }
+
+    fn sol_usdc_pool() -> PoolInfo {
+        PoolInfo {
+            token_mint_0: Pubkey::new_unique(),
+            token_mint_1: Pubkey::new_unique(),
+            observation_key: Pubkey::new_unique(),
+            mint_decimals_0: 9,
+            mint_decimals_1: 6,
+        }
+    }
+
+    #[test]
+    fn parses_pool_fields() {
+        let pool = sol_usdc_pool();
+        assert_eq!(parse_pool(&pool_data(&pool)).unwrap(), pool);
+    }
+
+    #[test]
+    fn twap_tick_wraps_around_observation_ring() {
+        let pool_id = Pubkey::new_unique();
+        // 最新观察点位于索引 1，窗口起点之前的观察点位于索引 99
+        let data = observation_data(
+            &pool_id,
+            1,
+            &[(98, 1_000, 0), (99, 1_400, 400 * -20_000), (0, 1_700, 700 * -20_000), (1, 2_000, 1_000 * -20_000)],
+        );
+
+        assert_eq!(twap_tick(&data, &pool_id, 600).unwrap(), (-20_000, 2_000));
+        // 窗口超出已有历史
+        assert!(twap_tick(&data, &pool_id, 1_500).is_err());
+        assert!(twap_tick(&data, &Pubkey::new_unique(), 600).is_err());
+    }
+
+    #[test]
+    fn tick_price_matches_both_pool_directions() {
+        let pool = sol_usdc_pool();
+        // tick -20_000 约为 0.1353 个 token_1 最小单位 / token_0 最小单位，即 1 SOL ≈ 135.35 USDC
+        let sol_price = tick_to_precision_price(-20_000, &pool, &pool.token_mint_0).unwrap();
+        assert_eq!(sol_price / 1_000_000_000_000_000, 135_348);
+
+        let usdc_price = tick_to_precision_price(-20_000, &pool, &pool.token_mint_1).unwrap();
+        assert_eq!(usdc_price / 1_000_000_000_000, 7_388);
+
+        assert!(tick_to_precision_price(-20_000, &pool, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn spot_amount_out_follows_sqrt_price() {
+        let one = 1u128 << 64;
+        assert_eq!(spot_amount_out(one, 1_000, true), Some(1_000));
+        // sqrt_price = 2，即 1 个 token_0 = 4 个 token_1
+        assert_eq!(spot_amount_out(one * 2, 1_000, true), Some(4_000));
+        assert_eq!(spot_amount_out(one * 2, 4_000, false), Some(1_000));
+        assert_eq!(spot_amount_out(0, 1_000, true), None);
+        // 输出超出 u64 时返回 None
+        assert_eq!(spot_amount_out(one << 16, u64::MAX, true), None);
+    }
+
+    #[test]
+    fn mul_div_uses_full_precision() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 80), Some(1 << 120));
+        assert_eq!(mul_div(7, 3, 2), Some(10));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+    }
+
+    #[test]
+    fn sqrt_price_at_tick_matches_clmm() {
+        assert_eq!(sqrt_price_x64_at_tick(0), Some(Q64));
+        // Byreal (Raydium) CLMM 的 MIN_SQRT_PRICE_X64 / MAX_SQRT_PRICE_X64
+        assert_eq!(sqrt_price_x64_at_tick(MIN_TICK), Some(4_295_048_016));
+        assert_eq!(
+            sqrt_price_x64_at_tick(MAX_TICK),
+            Some(79_226_673_521_066_979_257_578_248_091)
+        );
+        assert_eq!(sqrt_price_x64_at_tick(MAX_TICK + 1), None);
+
+        // 正负 tick 互为倒数
+        let up = sqrt_price_x64_at_tick(10_000).unwrap();
+        let down = sqrt_price_x64_at_tick(-10_000).unwrap();
+        assert!(mul_div(up, down, Q64).unwrap().abs_diff(Q64) <= 1);
+    }
+}
@@ -0,0 +1,278 @@
+// 预言机价格相关的内部接口与计算
+// 所有价格源（Pyth、Switchboard 等）解析后统一转换为 OraclePrice，
+// 金库的风控检查和估值只依赖这里的结构体和函数
+
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+pub mod clmm_twap;
+pub mod pyth;
+pub mod switchboard;
+
+/// 基点分母 (10000 = 100%)
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// 统一的预言机价格，真实价格 = price * 10^expo
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OraclePrice {
+    /// 价格
+    pub price: i64,
+    /// 置信区间，与 price 使用相同的指数
+    pub conf: u64,
+    /// 价格指数
+    pub expo: i32,
+    /// 价格发布时间戳 (秒)
+    pub publish_time: i64,
+}
+
+/// 检查价格的时效性与置信区间
+/// max_confidence_bps 为置信区间相对价格的最大比例 (基点)
+pub fn validate_price(
+    price: &OraclePrice,
+    now: i64,
+    max_staleness_seconds: u64,
+    max_confidence_bps: u16,
+) -> Result<()> {
+    require!(price.price > 0, ErrorCode::InvalidOraclePrice);
+
+    let age = now.saturating_sub(price.publish_time);
+    require!(
+        age >= 0 && (age as u64) <= max_staleness_seconds,
+        ErrorCode::StalePrice
+    );
+
+    let confidence_bps = (price.conf as u128) * (BPS_DENOMINATOR as u128) / (price.price as u128);
+    require!(
+        confidence_bps <= max_confidence_bps as u128,
+        ErrorCode::PriceConfidenceTooWide
+    );
+
+    Ok(())
+}
+
+/// 10 的整数次幂，溢出时返回 None
+fn pow10(exp: u32) -> Option<u128> {
+    10u128.checked_pow(exp)
+}
+
+/// 根据输入和输出代币的价格计算预言机隐含的输出金额
+/// amount_out = amount_in * price_in / price_out，并按指数与代币精度换算
+pub fn implied_amount_out(
+    amount_in: u64,
+    price_in: &OraclePrice,
+    decimals_in: u8,
+    price_out: &OraclePrice,
+    decimals_out: u8,
+) -> Option<u64> {
+    if price_in.price <= 0 || price_out.price <= 0 {
+        return None;
+    }
+
+    let mut numerator = (amount_in as u128).checked_mul(price_in.price as u128)?;
+    let mut denominator = price_out.price as u128;
+
+    let exp = price_in.expo - price_out.expo + decimals_out as i32 - decimals_in as i32;
+    if exp >= 0 {
+        numerator = numerator.checked_mul(pow10(exp as u32)?)?;
+    } else {
+        denominator = denominator.checked_mul(pow10(exp.unsigned_abs())?)?;
+    }
+
+    u64::try_from(numerator / denominator).ok()
+}
+
+/// 10^18 精度价格 (按完整代币计) 转换为 OraclePrice 时使用的价格指数
+pub const PRECISION_PRICE_EXPO: i32 = -12;
+
+/// 将 10^18 精度的价格转换为 OraclePrice
+pub fn from_precision_price(price: u128, publish_time: i64) -> Option<OraclePrice> {
+    let scaled = price / pow10((18 + PRECISION_PRICE_EXPO) as u32)?;
+    Some(OraclePrice {
+        price: i64::try_from(scaled).ok()?,
+        conf: 0,
+        expo: PRECISION_PRICE_EXPO,
+        publish_time,
+    })
+}
+
+/// 按 10^18 精度价格计算 amount_a (最小单位) 以代币 B 最小单位计的价值
+pub fn precision_price_value(amount_a: u64, price: u128, decimals_a: u8, decimals_b: u8) -> Option<u64> {
+    let numerator = (amount_a as u128)
+        .checked_mul(price)?
+        .checked_mul(pow10(decimals_b as u32)?)?;
+    let denominator = pow10(18)?.checked_mul(pow10(decimals_a as u32)?)?;
+    u64::try_from(numerator / denominator).ok()
+}
+
+/// 将价格统一换算为 10^18 精度
+pub fn to_precision_price(price: &OraclePrice) -> Option<u128> {
+    if price.price <= 0 {
+        return None;
+    }
+    let exp = price.expo + 18;
+    if exp >= 0 {
+        (price.price as u128).checked_mul(pow10(exp as u32)?)
+    } else {
+        Some(price.price as u128 / pow10(exp.unsigned_abs())?)
+    }
+}
+
+/// 取多个价格源的中位数，任一价格源偏离中位数超过 max_deviation_bps 时拒绝
+/// 返回价格的发布时间取所有价格源中最早的一个
+pub fn aggregate_median(prices: &[OraclePrice], max_deviation_bps: u16) -> Result<OraclePrice> {
+    require!(!prices.is_empty(), ErrorCode::OraclePriceUnavailable);
+
+    let mut normalized = prices
+        .iter()
+        .map(to_precision_price)
+        .collect::<Option<Vec<u128>>>()
+        .ok_or(ErrorCode::InvalidOraclePrice)?;
+    normalized.sort_unstable();
+
+    let middle = normalized.len() / 2;
+    let median = if normalized.len() % 2 == 0 {
+        normalized[middle - 1] / 2 + normalized[middle] / 2
+    } else {
+        normalized[middle]
+    };
+    require!(median > 0, ErrorCode::InvalidOraclePrice);
+
+    for price in &normalized {
+        let deviation_bps = price.abs_diff(median).saturating_mul(BPS_DENOMINATOR as u128) / median;
+        require!(
+            deviation_bps <= max_deviation_bps as u128,
+            ErrorCode::PriceSourcesDisagree
+        );
+    }
+
+    let publish_time = prices.iter().map(|price| price.publish_time).min().unwrap_or_default();
+    from_precision_price(median, publish_time).ok_or_else(|| error!(ErrorCode::InvalidOraclePrice))
+}
+
+/// 以 quote 计价的 base 价格 (按完整代币计，10^18 精度)，即 price_base / price_quote
+pub fn relative_precision_price(price_base: &OraclePrice, price_quote: &OraclePrice) -> Option<u128> {
+    let scale = pow10(18)?;
+    let mut numerator = to_precision_price(price_base)?;
+    let mut denominator = to_precision_price(price_quote)?;
+    // 分子乘以 10^18 溢出时同时缩小分子分母，保持比值不变
+    while numerator.checked_mul(scale).is_none() {
+        numerator /= 10;
+        denominator /= 10;
+    }
+    if denominator == 0 {
+        return None;
+    }
+    Some(numerator * scale / denominator)
+}
+
+/// 按基点扣除滑点容忍度后的金额
+pub fn apply_slippage_bps(amount: u64, slippage_bps: u16) -> u64 {
+    let slippage_bps = (slippage_bps as u64).min(BPS_DENOMINATOR);
+    ((amount as u128) * ((BPS_DENOMINATOR - slippage_bps) as u128) / BPS_DENOMINATOR as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(price: i64, conf: u64, expo: i32, publish_time: i64) -> OraclePrice {
+        OraclePrice {
+            price,
+            conf,
+            expo,
+            publish_time,
+        }
+    }
+
+    #[test]
+    fn validate_price_enforces_staleness_and_confidence() {
+        let sol_usd = price(150_00000000, 7_500_000, -8, 1_000);
+
+        assert!(validate_price(&sol_usd, 1_060, 60, 50).is_ok());
+        assert!(validate_price(&sol_usd, 1_061, 60, 50).is_err());
+        // 置信区间为价格的 5 个基点
+        assert!(validate_price(&sol_usd, 1_000, 60, 4).is_err());
+        assert!(validate_price(&price(0, 0, -8, 1_000), 1_000, 60, 50).is_err());
+    }
+
+    #[test]
+    fn implied_amount_out_scales_by_exponent_and_decimals() {
+        let sol_usd = price(150_00000000, 0, -8, 0);
+        let usdc_usd = price(1_00000000, 0, -8, 0);
+
+        // 2 SOL (9 位精度) -> 300 USDC (6 位精度)
+        assert_eq!(
+            implied_amount_out(2_000_000_000, &sol_usd, 9, &usdc_usd, 6),
+            Some(300_000_000)
+        );
+        // 300 USDC -> 2 SOL
+        assert_eq!(
+            implied_amount_out(300_000_000, &usdc_usd, 6, &sol_usd, 9),
+            Some(2_000_000_000)
+        );
+        assert_eq!(implied_amount_out(1, &price(0, 0, -8, 0), 6, &sol_usd, 9), None);
+    }
+
+    #[test]
+    fn precision_price_conversions() {
+        // 1 SOL = 150.5 USDC
+        let price = 150_500_000_000_000_000_000u128;
+
+        assert_eq!(
+            from_precision_price(price, 7),
+            Some(OraclePrice {
+                price: 150_500_000_000_000,
+                conf: 0,
+                expo: PRECISION_PRICE_EXPO,
+                publish_time: 7,
+            })
+        );
+        // 2 SOL (9 位精度) -> 301 USDC (6 位精度)
+        assert_eq!(precision_price_value(2_000_000_000, price, 9, 6), Some(301_000_000));
+    }
+
+    #[test]
+    fn aggregate_median_picks_middle_and_rejects_disagreement() {
+        let pyth = price(150_00000000, 0, -8, 100);
+        let switchboard = price(151_000_000_000_000, 0, -12, 90);
+        let twap = price(149_500_000_000_000, 0, -12, 95);
+
+        let median = aggregate_median(&[pyth, switchboard, twap], 100).unwrap();
+        assert_eq!(to_precision_price(&median), to_precision_price(&pyth));
+        assert_eq!(median.publish_time, 90);
+
+        // 两个价格源取平均值
+        let median = aggregate_median(&[switchboard, twap], 100).unwrap();
+        assert_eq!(to_precision_price(&median), Some(150_250_000_000_000_000_000));
+
+        // 151 相对 150 偏离约 66 个基点
+        assert!(aggregate_median(&[pyth, switchboard, twap], 50).is_err());
+        assert!(aggregate_median(&[], 100).is_err());
+    }
+
+    #[test]
+    fn relative_precision_price_divides_prices() {
+        let sol = price(150_00000000, 0, -8, 100);
+        let usdc = price(99_990_000, 0, -8, 100);
+        // 150 / 0.9999
+        assert_eq!(relative_precision_price(&sol, &usdc), Some(150_015_001_500_150_015_001));
+
+        // 高价代币相对低价代币不会溢出
+        let btc = price(100_000_000_000_000_000, 0, -12, 100);
+        let bonk = price(20, 0, -12, 100);
+        assert_eq!(
+            relative_precision_price(&btc, &bonk),
+            Some(5_000_000_000_000_000 * 1_000_000_000_000_000_000)
+        );
+
+        assert_eq!(relative_precision_price(&sol, &price(0, 0, -8, 100)), None);
+    }
+
+    #[test]
+    fn apply_slippage_bps_rounds_down() {
+        assert_eq!(apply_slippage_bps(10_000, 50), 9_950);
+        assert_eq!(apply_slippage_bps(999, 100), 989);
+        assert_eq!(apply_slippage_bps(10_000, 20_000), 0);
+    }
+}
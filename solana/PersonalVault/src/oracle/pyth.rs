@@ -0,0 +1,157 @@
+// Pyth 拉取式预言机 (Pyth Solana Receiver) 价格账户解析
+// 账户布局参考 pyth-solana-receiver-sdk 的 PriceUpdateV2:
+// discriminator(8) + write_authority(32) + verification_level(1 或 2)
+// + PriceFeedMessage(32 + 8 + 8 + 4 + 8 + 8 + 8 + 8) + posted_slot(8)
+
+use anchor_lang::prelude::*;
+
+use super::OraclePrice;
+use crate::ErrorCode;
+
+/// Pyth Solana Receiver 程序 ID (主网与 devnet 相同)
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// PriceUpdateV2 账户标识符: sha256("account:PriceUpdateV2")[..8]
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+// VerificationLevel 枚举标签
+const VERIFICATION_LEVEL_PARTIAL: u8 = 0;
+const VERIFICATION_LEVEL_FULL: u8 = 1;
+
+/// 从字节切片中按偏移读取定长数组
+fn read_bytes<const N: usize>(data: &[u8], offset: &mut usize) -> Result<[u8; N]> {
+    let bytes: [u8; N] = data
+        .get(*offset..*offset + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(ErrorCode::InvalidPriceAccount)?;
+    *offset += N;
+    Ok(bytes)
+}
+
+/// 解析 PriceUpdateV2 账户数据，返回 feed_id 与价格
+/// 只接受经过完整 Wormhole 签名验证 (Full) 的价格
+pub fn parse_price_update(data: &[u8]) -> Result<([u8; 32], OraclePrice)> {
+    let mut offset = 0;
+
+    let discriminator: [u8; 8] = read_bytes(data, &mut offset)?;
+    require!(
+        discriminator == PRICE_UPDATE_V2_DISCRIMINATOR,
+        ErrorCode::InvalidPriceAccount
+    );
+
+    // write_authority
+    read_bytes::<32>(data, &mut offset)?;
+
+    let [verification_level] = read_bytes::<1>(data, &mut offset)?;
+    match verification_level {
+        VERIFICATION_LEVEL_FULL => {}
+        VERIFICATION_LEVEL_PARTIAL => return err!(ErrorCode::PriceNotFullyVerified),
+        _ => return err!(ErrorCode::InvalidPriceAccount),
+    }
+
+    let feed_id: [u8; 32] = read_bytes(data, &mut offset)?;
+    let price = i64::from_le_bytes(read_bytes(data, &mut offset)?);
+    let conf = u64::from_le_bytes(read_bytes(data, &mut offset)?);
+    let expo = i32::from_le_bytes(read_bytes(data, &mut offset)?);
+    let publish_time = i64::from_le_bytes(read_bytes(data, &mut offset)?);
+
+    Ok((
+        feed_id,
+        OraclePrice {
+            price,
+            conf,
+            expo,
+            publish_time,
+        },
+    ))
+}
+
+/// 从 Pyth 价格账户读取指定 feed_id 的价格
+pub fn load_price(account: &AccountInfo, feed_id: &[u8; 32]) -> Result<OraclePrice> {
+    require!(
+        *account.owner == PYTH_RECEIVER_PROGRAM_ID,
+        ErrorCode::InvalidPriceAccount
+    );
+
+    let data = account.try_borrow_data()?;
+    let (account_feed_id, price) = parse_price_update(&data)?;
+    require!(account_feed_id == *feed_id, ErrorCode::PriceFeedMismatch);
+
+    Ok(price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 构造一个 PriceUpdateV2 账户数据
+    fn price_update_data(verification_level: &[u8], feed_id: [u8; 32], price: &OraclePrice) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&PRICE_UPDATE_V2_DISCRIMINATOR);
+        data.extend_from_slice(&[7u8; 32]); // write_authority
+        data.extend_from_slice(verification_level);
+        data.extend_from_slice(&feed_id);
+        data.extend_from_slice(&price.price.to_le_bytes());
+        data.extend_from_slice(&price.conf.to_le_bytes());
+        data.extend_from_slice(&price.expo.to_le_bytes());
+        data.extend_from_slice(&price.publish_time.to_le_bytes());
+        data.extend_from_slice(&(price.publish_time - 1).to_le_bytes()); // prev_publish_time
+        data.extend_from_slice(&price.price.to_le_bytes()); // ema_price
+        data.extend_from_slice(&price.conf.to_le_bytes()); // ema_conf
+        data.extend_from_slice(&42u64.to_le_bytes()); // posted_slot
+        data
+    }
+
+    fn sol_usd() -> OraclePrice {
+        OraclePrice {
+            price: 15_012_345_678,
+            conf: 6_543_210,
+            expo: -8,
+            publish_time: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn parses_fully_verified_price_update() {
+        let data = price_update_data(&[VERIFICATION_LEVEL_FULL], [1u8; 32], &sol_usd());
+
+        let (feed_id, price) = parse_price_update(&data).unwrap();
+
+        assert_eq!(feed_id, [1u8; 32]);
+        assert_eq!(price, sol_usd());
+    }
+
+    #[test]
+    fn rejects_partially_verified_price_update() {
+        let data = price_update_data(&[VERIFICATION_LEVEL_PARTIAL, 3], [1u8; 32], &sol_usd());
+
+        assert!(parse_price_update(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_discriminator_and_truncated_data() {
+        let mut data = price_update_data(&[VERIFICATION_LEVEL_FULL], [1u8; 32], &sol_usd());
+        assert!(parse_price_update(&data[..60]).is_err());
+
+        data[0] ^= 0xff;
+        assert!(parse_price_update(&data).is_err());
+    }
+
+    #[test]
+    fn load_price_checks_owner_and_feed_id() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = price_update_data(&[VERIFICATION_LEVEL_FULL], [1u8; 32], &sol_usd());
+        let owner = PYTH_RECEIVER_PROGRAM_ID;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        assert_eq!(load_price(&account, &[1u8; 32]).unwrap(), sol_usd());
+        assert!(load_price(&account, &[2u8; 32]).is_err());
+
+        let wrong_owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = price_update_data(&[VERIFICATION_LEVEL_FULL], [1u8; 32], &sol_usd());
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &wrong_owner, false, 0);
+        assert!(load_price(&account, &[1u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+// Switchboard On-Demand 拉取式价格源 (PullFeedAccountData) 解析
+// 账户为 #[repr(C)] 零拷贝布局，参考 switchboard-on-demand 的 PullFeedAccountData:
+// discriminator(8) + submissions(32 * 64) + authority(32) + queue(32) + feed_hash(32)
+// + initialized_at(8) + permissions(8) + max_variance(8) + min_responses(4) + name(32)
+// + padding(2) + historical_result_idx(1) + min_sample_size(1) + last_update_timestamp(8)
+// + lut_slot(8) + reserved(32) + CurrentResult{ value(16), std_dev(16), ... }
+
+use anchor_lang::prelude::*;
+
+use super::OraclePrice;
+use crate::ErrorCode;
+
+// Switchboard On-Demand 程序 ID
+#[cfg(feature = "devnet")]
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey = pubkey!("Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2");
+#[cfg(not(feature = "devnet"))]
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+/// PullFeedAccountData 账户标识符: sha256("account:PullFeedAccountData")[..8]
+pub const PULL_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+
+// 各字段在账户数据中的偏移 (含 8 字节标识符)
+const LAST_UPDATE_TIMESTAMP_OFFSET: usize = 8 + 2208;
+const RESULT_VALUE_OFFSET: usize = 8 + 2256;
+const RESULT_STD_DEV_OFFSET: usize = RESULT_VALUE_OFFSET + 16;
+const RESULT_NUM_SAMPLES_OFFSET: usize = 8 + 2352;
+
+/// Switchboard 结果固定为 18 位小数
+const SWITCHBOARD_DECIMALS: u32 = 18;
+/// 转换为 OraclePrice 时使用的价格指数
+pub const SWITCHBOARD_PRICE_EXPO: i32 = -12;
+
+/// 从字节切片中按偏移读取定长数组
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or_else(|| error!(ErrorCode::InvalidPriceAccount))
+}
+
+/// 将 18 位小数的 i128 结果缩放到 SWITCHBOARD_PRICE_EXPO
+fn scale_value(value: i128) -> Result<i64> {
+    let divisor = 10i128.pow(SWITCHBOARD_DECIMALS - SWITCHBOARD_PRICE_EXPO.unsigned_abs());
+    i64::try_from(value / divisor).map_err(|_| error!(ErrorCode::InvalidOraclePrice))
+}
+
+/// 解析 PullFeedAccountData 账户数据中的当前结果
+pub fn parse_pull_feed(data: &[u8]) -> Result<OraclePrice> {
+    let discriminator: [u8; 8] = read_bytes(data, 0)?;
+    require!(discriminator == PULL_FEED_DISCRIMINATOR, ErrorCode::InvalidPriceAccount);
+
+    let [num_samples] = read_bytes::<1>(data, RESULT_NUM_SAMPLES_OFFSET)?;
+    require!(num_samples > 0, ErrorCode::InvalidOraclePrice);
+
+    let value = i128::from_le_bytes(read_bytes(data, RESULT_VALUE_OFFSET)?);
+    let std_dev = i128::from_le_bytes(read_bytes(data, RESULT_STD_DEV_OFFSET)?);
+    let publish_time = i64::from_le_bytes(read_bytes(data, LAST_UPDATE_TIMESTAMP_OFFSET)?);
+
+    Ok(OraclePrice {
+        price: scale_value(value)?,
+        conf: scale_value(std_dev)?.unsigned_abs(),
+        expo: SWITCHBOARD_PRICE_EXPO,
+        publish_time,
+    })
+}
+
+/// 从 Switchboard feed 账户读取价格
+pub fn load_price(account: &AccountInfo) -> Result<OraclePrice> {
+    require!(
+        *account.owner == SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+        ErrorCode::InvalidPriceAccount
+    );
+
+    let data = account.try_borrow_data()?;
+    parse_pull_feed(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 构造一个 PullFeedAccountData 账户数据
+    fn pull_feed_data(value: i128, std_dev: i128, num_samples: u8, last_update_timestamp: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 8 + 3208];
+        data[..8].copy_from_slice(&PULL_FEED_DISCRIMINATOR);
+        data[LAST_UPDATE_TIMESTAMP_OFFSET..LAST_UPDATE_TIMESTAMP_OFFSET + 8]
+            .copy_from_slice(&last_update_timestamp.to_le_bytes());
+        data[RESULT_VALUE_OFFSET..RESULT_VALUE_OFFSET + 16].copy_from_slice(&value.to_le_bytes());
+        data[RESULT_STD_DEV_OFFSET..RESULT_STD_DEV_OFFSET + 16].copy_from_slice(&std_dev.to_le_bytes());
+        data[RESULT_NUM_SAMPLES_OFFSET] = num_samples;
+        data
+    }
+
+    #[test]
+    fn parses_current_result_into_oracle_price() {
+        // 150.25 ± 0.05，18 位小数
+        let data = pull_feed_data(150_250_000_000_000_000_000, 50_000_000_000_000_000, 3, 1_700_000_000);
+
+        let price = parse_pull_feed(&data).unwrap();
+
+        assert_eq!(
+            price,
+            OraclePrice {
+                price: 150_250_000_000_000,
+                conf: 50_000_000_000,
+                expo: SWITCHBOARD_PRICE_EXPO,
+                publish_time: 1_700_000_000,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_empty_result_and_bad_discriminator() {
+        let data = pull_feed_data(1_000_000_000_000_000_000, 0, 0, 1_700_000_000);
+        assert!(parse_pull_feed(&data).is_err());
+
+        let mut data = pull_feed_data(1_000_000_000_000_000_000, 0, 1, 1_700_000_000);
+        data[0] ^= 0xff;
+        assert!(parse_pull_feed(&data).is_err());
+        assert!(parse_pull_feed(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn load_price_checks_owner() {
+        let key = Pubkey::new_unique();
+        let owner = SWITCHBOARD_ON_DEMAND_PROGRAM_ID;
+        let mut lamports = 0;
+        let mut data = pull_feed_data(2_000_000_000_000_000_000, 0, 1, 1_700_000_000);
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(load_price(&account).unwrap().price, 2_000_000_000_000);
+
+        let wrong_owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = pull_feed_data(2_000_000_000_000_000_000, 0, 1, 1_700_000_000);
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &wrong_owner, false, 0);
+        assert!(load_price(&account).is_err());
+    }
+}
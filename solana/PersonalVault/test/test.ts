@@ -516,10 +516,10 @@ async function setBot(vaultPda: PublicKey, newBotAddress: PublicKey): Promise<st
   }
 }
 
-// 设置管理员函数
-async function setAdmin(vaultPda: PublicKey, newAdmin: PublicKey): Promise<string> {
+// 提议新管理员函数 (新管理员需调用 accept_admin 完成转移)
+async function proposeAdmin(vaultPda: PublicKey, newAdmin: PublicKey): Promise<string> {
   try {
-    console.log("\n👤 设置管理员...");
+    console.log("\n👤 提议新管理员...");
     console.log("  新管理员地址:", newAdmin.toString());
 
     const connection = await checkConnection();
@@ -535,13 +535,13 @@ async function setAdmin(vaultPda: PublicKey, newAdmin: PublicKey): Promise<strin
             { pubkey: vaultPda, isSigner: false, isWritable: true },
             { pubkey: walletKeypair.publicKey, isSigner: true, isWritable: false },
           ],
-          data: serializeInstructionData("set_admin", newAdmin),
+          data: serializeInstructionData("propose_admin", newAdmin),
         })
       ),
       [walletKeypair]
     );
 
-    console.log("✅ 管理员设置成功!");
+    console.log("✅ 新管理员提议成功!");
     console.log("  交易签名:", tx);
 
     // 打印交易查看链接
@@ -551,7 +551,7 @@ async function setAdmin(vaultPda: PublicKey, newAdmin: PublicKey): Promise<strin
     return tx;
 
   } catch (error) {
-    console.error("❌ 设置管理员失败:", error);
+    console.error("❌ 提议新管理员失败:", error);
     throw error;
   }
 }
@@ -681,7 +681,7 @@ async function testInitialize() {
     //   console.log("👤 测试设置管理员功能...");
     //   console.log("  新管理员地址:", newAdminAddress.toString());
 
-    //   const setAdminTx = await setAdmin(vaultPda, newAdminAddress);
+    //   const setAdminTx = await proposeAdmin(vaultPda, newAdminAddress);
     //   console.log("✅ 设置管理员测试成功");
     //   console.log("  交易签名:", setAdminTx);
 
@@ -797,7 +797,7 @@ export {
   getVaultInfo,
   verifyBalanceChange,
   setBot,
-  setAdmin,
+  proposeAdmin,
   testInitialize,
   TEST_ADDRESSES,
   VAULT_PDA